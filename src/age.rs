@@ -0,0 +1,148 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use crate::error::SizeError;
+
+/// Which timestamp ages are measured from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeField {
+    /// Last modification of the contents (mtime)
+    #[default]
+    Modified,
+    /// Last read (atime), if the filesystem keeps it
+    Accessed,
+    /// Last change to the contents or the metadata (ctime)
+    Changed,
+}
+
+impl TimeField {
+    /// This timestamp from `metadata`, if the platform records it.
+    pub fn of(self, metadata: &fs::Metadata) -> Option<SystemTime> {
+        match self {
+            TimeField::Modified => metadata.modified().ok(),
+            TimeField::Accessed => metadata.accessed().ok(),
+            TimeField::Changed => changed(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn changed(metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    let since_epoch = Duration::new(
+        u64::try_from(metadata.ctime()).ok()?,
+        u32::try_from(metadata.ctime_nsec()).ok()?,
+    );
+    SystemTime::UNIX_EPOCH.checked_add(since_epoch)
+}
+
+// Without ctime, creation is the closest thing on offer
+#[cfg(not(unix))]
+fn changed(metadata: &fs::Metadata) -> Option<SystemTime> {
+    metadata.created().ok()
+}
+
+/// Format an age using the largest unit that keeps it readable, e.g. `45s`,
+/// `12h`, `90d` or `2.5y`.
+pub fn format_age(age: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = MINUTE * 60;
+    const DAY: u64 = HOUR * 24;
+    const YEAR: u64 = DAY * 365;
+
+    let secs = age.as_secs();
+    if secs >= YEAR {
+        format!("{:.1}y", secs as f64 / YEAR as f64)
+    } else if secs >= DAY {
+        format!("{}d", secs / DAY)
+    } else if secs >= HOUR {
+        format!("{}h", secs / HOUR)
+    } else if secs >= MINUTE {
+        format!("{}m", secs / MINUTE)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Parse a duration such as `90d`, `1.5h`, `2w` or `30` (seconds).
+///
+/// Units are `ms`, `s`, `m` (minutes), `h`, `d`, `w` and `y` (365 days), with
+/// `sec`, `min`, `hour`, `day`, `week` and `year` and their plurals, and
+/// `second` and `minute` spelled out, accepted as well.
+pub fn parse_duration(duration_str: &str) -> Result<Duration, SizeError> {
+    let duration_str = duration_str.trim().to_lowercase();
+
+    if duration_str.is_empty() {
+        return Err(SizeError::DurationError("Empty duration string".to_string()));
+    }
+
+    let split = duration_str
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(duration_str.len());
+    let (num_str, unit) = duration_str.split_at(split);
+    let num_str = num_str.trim();
+
+    let num = num_str
+        .parse::<f64>()
+        .map_err(|_| SizeError::DurationError(format!("Invalid number: {}", num_str)))?;
+
+    let multiplier: f64 = match unit {
+        "ms" => 0.001,
+        "" | "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+        "h" | "hour" | "hours" => 60.0 * 60.0,
+        "d" | "day" | "days" => 24.0 * 60.0 * 60.0,
+        "w" | "week" | "weeks" => 7.0 * 24.0 * 60.0 * 60.0,
+        "y" | "year" | "years" => 365.0 * 24.0 * 60.0 * 60.0,
+        _ => return Err(SizeError::DurationError(format!("Unknown unit: {}", unit))),
+    };
+
+    Duration::try_from_secs_f64(num * multiplier)
+        .map_err(|_| SizeError::DurationError(format!("Duration out of range: {}", duration_str)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(duration: &str) -> f64 {
+        parse_duration(duration).unwrap().as_secs_f64()
+    }
+
+    #[test]
+    fn parses_every_unit_spelling() {
+        assert_eq!(secs("30"), 30.0);
+        assert_eq!(secs("30s"), 30.0);
+        assert_eq!(secs("2 secs"), 2.0);
+        assert_eq!(secs("1second"), 1.0);
+        assert_eq!(secs("5m"), 300.0);
+        assert_eq!(secs("5mins"), 300.0);
+        assert_eq!(secs("1 Minute"), 60.0);
+        assert_eq!(secs("1.5h"), 5400.0);
+        assert_eq!(secs("2hours"), 7200.0);
+        assert_eq!(secs("90d"), 90.0 * 86400.0);
+        assert_eq!(secs("2 weeks"), 14.0 * 86400.0);
+        assert_eq!(secs("1y"), 365.0 * 86400.0);
+        assert_eq!(secs("3years"), 3.0 * 365.0 * 86400.0);
+    }
+
+    #[test]
+    fn milliseconds_are_not_minutes() {
+        assert_eq!(parse_duration("5ms").unwrap(), Duration::from_millis(5));
+        assert_eq!(parse_duration("1500ms").unwrap(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for bad in ["", "d", "5ss", "5mss", "5dayss", "5q", "1.2.3h", "-1d", "99999999999999999999y"] {
+            assert!(parse_duration(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn formats_ages_in_the_largest_whole_unit() {
+        assert_eq!(format_age(Duration::from_secs(59)), "59s");
+        assert_eq!(format_age(Duration::from_secs(90)), "1m");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400)), "3d");
+    }
+}
@@ -0,0 +1,94 @@
+//! Totals grouped by a key derived from each file, such as its extension.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use sizetree::{scan_streaming, Breakdown, ScanOptions};
+//! use sizetree::breakdown::extension_of;
+//!
+//! let mut by_extension = Breakdown::default();
+//! scan_streaming(Path::new("."), ScanOptions::default(), &mut |entry| {
+//!     if !entry.is_dir {
+//!         by_extension.add(&extension_of(entry.path), entry.size, entry.disk_size);
+//!     }
+//!     Ok(())
+//! })?;
+//! # Ok::<(), sizetree::SizeError>(())
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::render::{RenderOptions, SortOrder};
+
+/// What the files sharing one key add up to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroupTotals {
+    /// Apparent bytes
+    pub size: u64,
+    /// Bytes allocated on disk
+    pub disk_size: u64,
+    pub files: u64,
+}
+
+impl GroupTotals {
+    /// The size used for filtering and sorting under the selected measure.
+    pub fn size_under(&self, options: &RenderOptions) -> u64 {
+        options.measured(self.size, self.disk_size)
+    }
+}
+
+/// Files counted once each under a key of the caller's choosing.
+#[derive(Debug, Clone, Default)]
+pub struct Breakdown {
+    groups: HashMap<String, GroupTotals>,
+    total: GroupTotals,
+}
+
+impl Breakdown {
+    /// Count one file of `size` apparent and `disk_size` allocated bytes under `key`.
+    pub fn add(&mut self, key: &str, size: u64, disk_size: u64) {
+        if !self.groups.contains_key(key) {
+            self.groups.insert(key.to_string(), GroupTotals::default());
+        }
+        for totals in [self.groups.get_mut(key).unwrap(), &mut self.total] {
+            totals.size += size;
+            totals.disk_size += disk_size;
+            totals.files += 1;
+        }
+    }
+
+    /// Every file added, whatever its key.
+    pub fn total(&self) -> GroupTotals {
+        self.total
+    }
+
+    /// The groups that pass `--min-size`, in the order `options` asks for.
+    pub fn rows(&self, options: &RenderOptions) -> Vec<(&str, GroupTotals)> {
+        let mut rows: Vec<(&str, GroupTotals)> = self
+            .groups
+            .iter()
+            .map(|(key, totals)| (key.as_str(), *totals))
+            .filter(|(_, totals)| totals.size_under(options) >= options.min_size)
+            .collect();
+
+        // Name order first, which the stable sorts keep for ties
+        rows.sort_by(|a, b| a.0.cmp(b.0));
+        match options.sort {
+            SortOrder::Size => rows.sort_by_key(|(_, totals)| Reverse(totals.size_under(options))),
+            SortOrder::Count => rows.sort_by_key(|(_, totals)| Reverse(totals.files)),
+            SortOrder::Name => {}
+        }
+
+        rows
+    }
+}
+
+/// The key `--by-extension` groups a file under: its extension, lowercased and
+/// with the dot, or `(none)`. Dotfiles such as `.bashrc` have no extension.
+pub fn extension_of(path: &Path) -> String {
+    match path.extension() {
+        Some(extension) => format!(".{}", extension.to_string_lossy().to_lowercase()),
+        None => "(none)".to_string(),
+    }
+}
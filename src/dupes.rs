@@ -0,0 +1,320 @@
+//! Finding files with identical contents in a scanned tree.
+//!
+//! Candidates are narrowed in stages so most files are never read: first by
+//! size, which the scan already knows, then by a hash of their first and last
+//! few kilobytes, and then by a hash of their whole contents. The hashes are
+//! not collision-resistant, so files still grouped together are finally
+//! compared byte for byte before being reported as copies. Paths that
+//! lead to the same inode, through hard links or followed symlinks, are one
+//! file, not duplicates, so only the first of them in tree order is considered.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::error::ScanError;
+use crate::tree::FileInfo;
+
+// Bytes hashed at each end of a file in the partial stage
+const PARTIAL_BLOCK: u64 = 4096;
+
+/// Files whose contents are identical.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSet {
+    /// Size of each copy in bytes
+    pub size: u64,
+    /// Every copy, in tree order
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    /// Bytes that keeping a single copy would free.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Find every set of two or more files under `root` with identical contents,
+/// ignoring files smaller than `min_size` (and always empty files).
+///
+/// Expects `root` to come from a scan with [`HardLinkPolicy::First`](crate::HardLinkPolicy::First),
+/// so the first link to each inode still carries its full size. Sets are
+/// ordered by wasted bytes, largest first. Files that could not be read are
+/// left out and returned as errors.
+pub fn find_duplicates(root: &FileInfo, min_size: u64) -> (Vec<DuplicateSet>, Vec<ScanError>) {
+    let mut by_size: HashMap<u64, Vec<&Path>> = HashMap::new();
+    collect(root, min_size.max(1), &mut by_size);
+
+    let mut errors = Vec::new();
+    let mut sets = Vec::new();
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        let paths = distinct_files(paths, &mut errors);
+        if paths.len() < 2 {
+            continue;
+        }
+
+        // Small files are read whole by the partial stage, so a full hash adds nothing
+        let whole = size <= 2 * PARTIAL_BLOCK;
+        for group in split_by(paths, &mut errors, |path| partial_hash(path, size)) {
+            let groups = if whole {
+                vec![group]
+            } else {
+                split_by(group.iter().map(PathBuf::as_path).collect(), &mut errors, full_hash)
+            };
+            for group in groups {
+                for paths in split_identical(group, &mut errors) {
+                    sets.push(DuplicateSet { size, paths });
+                }
+            }
+        }
+    }
+
+    for set in &mut sets {
+        set.paths.sort();
+    }
+    sets.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.paths.cmp(&b.paths)));
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    (sets, errors)
+}
+
+// Regular files by size, in tree order, skipping symlinks
+fn collect<'a>(node: &'a FileInfo, min_size: u64, by_size: &mut HashMap<u64, Vec<&'a Path>>) {
+    if node.is_dir {
+        for child in &node.children {
+            collect(child, min_size, by_size);
+        }
+        return;
+    }
+    if node.symlink_target.is_some() || node.size < min_size {
+        return;
+    }
+    by_size.entry(node.size).or_default().push(&node.path);
+}
+
+// Keep the first path, in tree order, to each file on disk. The scan only
+// records the inode of files with several links, but a followed symlinked
+// directory reaches any file a second time, so every candidate is looked up.
+fn distinct_files<'a>(paths: Vec<&'a Path>, errors: &mut Vec<ScanError>) -> Vec<&'a Path> {
+    let mut seen = HashSet::new();
+    let mut distinct = Vec::new();
+    for path in paths {
+        match fs::metadata(path) {
+            Ok(metadata) => {
+                if file_id(&metadata).is_none_or(|id| seen.insert(id)) {
+                    distinct.push(path);
+                }
+            }
+            Err(err) => errors.push(ScanError::new(path.to_path_buf(), &err)),
+        }
+    }
+    distinct
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Group `paths` by `hash`, keeping only groups of two or more
+fn split_by(
+    paths: Vec<&Path>,
+    errors: &mut Vec<ScanError>,
+    hash: impl Fn(&Path) -> io::Result<(u64, u64)>,
+) -> Vec<Vec<PathBuf>> {
+    let mut groups: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match hash(path) {
+            Ok(digest) => groups.entry(digest).or_default().push(path.to_path_buf()),
+            Err(err) => errors.push(ScanError::new(path.to_path_buf(), &err)),
+        }
+    }
+    groups.into_values().filter(|group| group.len() > 1).collect()
+}
+
+// Two SipHash states, one primed with an extra byte, give a 128-bit digest.
+// Their key is fixed and public, so a match only narrows the candidates down;
+// `split_identical` has the final say.
+struct ContentHasher {
+    low: DefaultHasher,
+    high: DefaultHasher,
+}
+
+impl ContentHasher {
+    fn new() -> Self {
+        let low = DefaultHasher::new();
+        let mut high = DefaultHasher::new();
+        high.write_u8(0xff);
+        ContentHasher { low, high }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.low.write(bytes);
+        self.high.write(bytes);
+    }
+
+    fn finish(&self) -> (u64, u64) {
+        (self.low.finish(), self.high.finish())
+    }
+}
+
+// The first and last blocks of a file, or all of it when it is small
+fn partial_hash(path: &Path, size: u64) -> io::Result<(u64, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = ContentHasher::new();
+    let mut buffer = vec![0; (2 * PARTIAL_BLOCK) as usize];
+
+    if size <= 2 * PARTIAL_BLOCK {
+        let read = read_full(&mut file, &mut buffer)?;
+        hasher.write(&buffer[..read]);
+    } else {
+        let (head, tail) = buffer.split_at_mut(PARTIAL_BLOCK as usize);
+        let read = read_full(&mut file, head)?;
+        hasher.write(&head[..read]);
+        file.seek(SeekFrom::Start(size - PARTIAL_BLOCK))?;
+        let read = read_full(&mut file, tail)?;
+        hasher.write(&tail[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+fn full_hash(path: &Path) -> io::Result<(u64, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = ContentHasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..read]);
+    }
+}
+
+// Split `group`, whose hashes all matched, into sets of files that are equal
+// byte for byte, keeping only sets of two or more
+fn split_identical(group: Vec<PathBuf>, errors: &mut Vec<ScanError>) -> Vec<Vec<PathBuf>> {
+    let mut sets: Vec<Vec<PathBuf>> = Vec::new();
+    'paths: for path in group {
+        for set in &mut sets {
+            match same_contents(&set[0], &path) {
+                Ok(true) => {
+                    set.push(path);
+                    continue 'paths;
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    errors.push(err);
+                    continue 'paths;
+                }
+            }
+        }
+        sets.push(vec![path]);
+    }
+    sets.into_iter().filter(|set| set.len() > 1).collect()
+}
+
+fn same_contents(a: &Path, b: &Path) -> Result<bool, ScanError> {
+    let open = |path: &Path| File::open(path).map_err(|err| ScanError::new(path.to_path_buf(), &err));
+    let (mut file_a, mut file_b) = (open(a)?, open(b)?);
+    let mut buffer_a = vec![0; 64 * 1024];
+    let mut buffer_b = vec![0; 64 * 1024];
+    loop {
+        let read_a = read_full(&mut file_a, &mut buffer_a).map_err(|err| ScanError::new(a.to_path_buf(), &err))?;
+        let read_b = read_full(&mut file_b, &mut buffer_b).map_err(|err| ScanError::new(b.to_path_buf(), &err))?;
+        if buffer_a[..read_a] != buffer_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// Like `read_exact`, but a file that shrank since the scan just reads short
+fn read_full(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{scan, HardLinkPolicy, ScanOptions, SymlinkPolicy};
+
+    #[test]
+    fn one_file_reached_by_several_paths_is_not_a_duplicate() {
+        let root = std::env::temp_dir().join(format!("sizetree-dupes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::create_dir_all(root.join("d")).unwrap();
+        fs::write(root.join("a/orig.bin"), vec![7u8; 100]).unwrap();
+        fs::hard_link(root.join("a/orig.bin"), root.join("a/hard.bin")).unwrap();
+        fs::write(root.join("d/copy.bin"), vec![7u8; 100]).unwrap();
+        // Only one link, but also reachable through the followed directory symlink
+        fs::write(root.join("a/solo.bin"), vec![9u8; 100_000]).unwrap();
+        std::os::unix::fs::symlink("../a", root.join("c/link")).unwrap();
+
+        let options = ScanOptions {
+            hard_links: HardLinkPolicy::First,
+            symlinks: SymlinkPolicy::Always,
+            ..ScanOptions::default()
+        };
+        let tree = scan(&root, options).unwrap();
+        let (sets, errors) = find_duplicates(&tree.root, 1);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            sets,
+            vec![DuplicateSet {
+                size: 100,
+                paths: vec![root.join("a/hard.bin"), root.join("d/copy.bin")],
+            }]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn files_grouped_by_hash_are_compared_byte_for_byte() {
+        let root = std::env::temp_dir().join(format!("sizetree-dupes-bytes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        // Larger than one comparison buffer, differing only in the last byte
+        let contents = vec![1u8; 100_000];
+        let mut different = contents.clone();
+        *different.last_mut().unwrap() = 2;
+        fs::write(root.join("a"), &contents).unwrap();
+        fs::write(root.join("b"), &different).unwrap();
+        fs::write(root.join("c"), &contents).unwrap();
+
+        // As if all three had collided in the hash stages
+        let group = vec![root.join("a"), root.join("b"), root.join("c")];
+        let mut errors = Vec::new();
+        assert_eq!(
+            split_identical(group, &mut errors),
+            vec![vec![root.join("a"), root.join("c")]]
+        );
+        assert!(errors.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by the scanner, the size and duration helpers and snapshot loading.
+#[derive(Debug)]
+pub enum SizeError {
+    ParseError(String),
+    /// A duration such as `--older-than 90d` could not be read
+    DurationError(String),
+    /// A snapshot file is malformed or from an unsupported version
+    SnapshotError(String),
+    IoError(io::Error),
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeError::ParseError(msg) => write!(f, "Size parsing error: {}", msg),
+            SizeError::DurationError(msg) => write!(f, "Duration parsing error: {}", msg),
+            SizeError::SnapshotError(msg) => write!(f, "Snapshot error: {}", msg),
+            SizeError::IoError(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SizeError {
+    fn from(error: io::Error) -> Self {
+        SizeError::IoError(error)
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+/// A path that could not be read during a scan, leaving the totals above it incomplete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    pub(crate) fn new(path: PathBuf, error: &io::Error) -> Self {
+        ScanError {
+            path,
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
@@ -0,0 +1,242 @@
+//! Gitignore-style glob patterns.
+//!
+//! A pattern without a `/` matches a name at any depth, while one with a
+//! leading or inner `/` is anchored to the scan root. A trailing `/` restricts
+//! it to directories. `*` and `?` stop at `/`, `[a-z]` and `[!a-z]` match
+//! character classes, `**` as a whole component matches any number of
+//! directories, and `\` escapes the next character. A trailing `/**` matches
+//! everything inside a directory but not the directory itself. A leading `!`
+//! negates the pattern, so it re-includes what an earlier pattern matched.
+
+/// One compiled glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    original: String,
+    negated: bool,
+    dir_only: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `**`: zero or more whole components
+    AnyDirs,
+    /// One component, matched with `*`, `?` and classes
+    Name(Vec<char>),
+}
+
+impl Pattern {
+    /// Compile `pattern`. Malformed classes are matched literally, as git does.
+    pub fn new(pattern: &str) -> Pattern {
+        let original = pattern.to_string();
+        let mut pattern = pattern.trim_end();
+
+        // A leading backslash escapes a literal '!' or '#'
+        let negated = pattern.starts_with('!');
+        if negated || pattern.starts_with("\\!") || pattern.starts_with("\\#") {
+            pattern = &pattern[1..];
+        }
+
+        let dir_only = pattern.len() > 1 && pattern.ends_with('/');
+        if dir_only {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+        let mut segments = Vec::new();
+        if !anchored {
+            segments.push(Segment::AnyDirs);
+        }
+        let parts: Vec<&str> = pattern.split('/').collect();
+        for (i, part) in parts.iter().enumerate() {
+            if *part == "**" && i > 0 && i == parts.len() - 1 {
+                // A trailing `/**` matches everything inside, but not the directory itself
+                segments.push(Segment::Name(vec!['*']));
+                segments.push(Segment::AnyDirs);
+            } else if *part == "**" {
+                segments.push(Segment::AnyDirs);
+            } else {
+                segments.push(Segment::Name(part.chars().collect()));
+            }
+        }
+
+        Pattern {
+            original,
+            negated,
+            dir_only,
+            segments,
+        }
+    }
+
+    /// The pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
+
+    /// Whether the pattern started with `!`.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Whether `path`, relative to the root and `/`-separated, matches,
+    /// ignoring negation.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let components: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        match_segments(&self.segments, &components)
+    }
+}
+
+/// Whether the last pattern in `patterns` that matches `path` is a positive one.
+pub fn matches_any(patterns: &[Pattern], path: &str, is_dir: bool) -> bool {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern.matches(path, is_dir))
+        .is_some_and(|pattern| !pattern.is_negated())
+}
+
+fn match_segments(segments: &[Segment], components: &[&str]) -> bool {
+    match segments.first() {
+        None => components.is_empty(),
+        Some(Segment::AnyDirs) => {
+            (0..=components.len()).any(|skip| match_segments(&segments[1..], &components[skip..]))
+        }
+        Some(Segment::Name(pattern)) => match components.first() {
+            Some(component) => {
+                let name: Vec<char> = component.chars().collect();
+                match_name(pattern, &name) && match_segments(&segments[1..], &components[1..])
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_name(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => {
+            let rest = &pattern[1..];
+            (0..=name.len()).any(|skip| match_name(rest, &name[skip..]))
+        }
+        Some('?') => !name.is_empty() && match_name(&pattern[1..], &name[1..]),
+        Some('[') => match match_class(pattern, name.first().copied()) {
+            Some((matched, len)) => matched && match_name(&pattern[len..], &name[1..]),
+            // No closing bracket, so the '[' is literal
+            None => name.first() == Some(&'[') && match_name(&pattern[1..], &name[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && match_name(&pattern[2..], &name[1..])
+        }
+        Some(c) => name.first() == Some(c) && match_name(&pattern[1..], &name[1..]),
+    }
+}
+
+// Match `c` against the class at the start of `pattern`, returning whether it
+// matched and how many pattern characters the class used
+fn match_class(pattern: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        if start == ']' && !first {
+            break;
+        }
+        first = false;
+
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|end| *end != ']') {
+            let end = pattern[i + 2];
+            matched |= c.is_some_and(|c| start <= c && c <= end);
+            i += 3;
+        } else {
+            matched |= c == Some(start);
+            i += 1;
+        }
+    }
+
+    Some((c.is_some() && matched != negated, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Pattern::new(pattern).matches(path, false)
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        assert!(matches("*.log", "app.log"));
+        assert!(matches("*.log", "a/b/app.log"));
+        assert!(!matches("*.log", "app.log.old"));
+        assert!(matches("target", "crates/x/target"));
+        assert!(matches("?.txt", "dir/a.txt"));
+        assert!(!matches("?.txt", "ab.txt"));
+    }
+
+    #[test]
+    fn slashes_anchor_to_the_root() {
+        assert!(matches("/build", "build"));
+        assert!(!matches("/build", "src/build"));
+        assert!(matches("doc/*.md", "doc/intro.md"));
+        assert!(!matches("doc/*.md", "src/doc/intro.md"));
+        assert!(!matches("doc/*.md", "doc/sub/intro.md"));
+    }
+
+    #[test]
+    fn double_stars_span_directories() {
+        assert!(matches("**/cache", "cache"));
+        assert!(matches("**/cache", "a/b/cache"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "x/a/b"));
+        assert!(matches("a/**", "a/x"));
+        assert!(matches("a/**", "a/x/y"));
+        assert!(!matches("a/**", "a"));
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_directories() {
+        let pattern = Pattern::new("logs/");
+        assert!(pattern.matches("logs", true));
+        assert!(pattern.matches("var/logs", true));
+        assert!(!pattern.matches("logs", false));
+    }
+
+    #[test]
+    fn classes_and_escapes() {
+        assert!(matches("file[0-9]", "file7"));
+        assert!(!matches("file[0-9]", "filex"));
+        assert!(matches("file[!0-9]", "filex"));
+        assert!(!matches("file[^0-9]", "file7"));
+        assert!(matches("[]a]", "]"));
+        assert!(matches("a[b", "a[b"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        assert!(matches("\\!keep", "!keep"));
+        assert!(!Pattern::new("\\!keep").is_negated());
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let patterns = [Pattern::new("*.log"), Pattern::new("!keep.log")];
+        assert!(matches_any(&patterns, "app.log", false));
+        assert!(!matches_any(&patterns, "keep.log", false));
+        assert!(!matches_any(&patterns, "readme", false));
+
+        let patterns = [Pattern::new("!keep.log"), Pattern::new("*.log")];
+        assert!(matches_any(&patterns, "keep.log", false));
+    }
+}
@@ -0,0 +1,97 @@
+//! Counting hard-linked files once per scan.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::tree::{FileInfo, HardLink};
+
+/// How the size of a file reachable through several hard links is attributed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HardLinkPolicy {
+    /// The first path in tree order carries the whole size, later links count as 0
+    #[default]
+    First,
+    /// Every link found in the scan carries an equal share of the size
+    Split,
+}
+
+/// Rewrite the sizes of hard-linked files under `root` so each inode is
+/// counted once, then refresh the directory totals above them.
+///
+/// Returns the number of apparent bytes that counting every link would have
+/// added on top of a single copy.
+pub(crate) fn dedup_hard_links(root: &mut FileInfo, policy: HardLinkPolicy) -> u64 {
+    let mut links: HashMap<(u64, u64), u64> = HashMap::new();
+    count_links(root, &mut links);
+
+    let shared_bytes = shared_bytes(root, &links, &mut HashSet::new());
+    let mut seen = HashMap::new();
+    apportion(root, policy, &links, &mut seen);
+    shared_bytes
+}
+
+fn count_links(node: &FileInfo, links: &mut HashMap<(u64, u64), u64>) {
+    if let Some(link) = node.hard_link {
+        *links.entry(key(link)).or_insert(0) += 1;
+    }
+    for child in &node.children {
+        count_links(child, links);
+    }
+}
+
+fn shared_bytes(node: &FileInfo, links: &HashMap<(u64, u64), u64>, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    let mut total = 0;
+    if let Some(link) = node.hard_link {
+        if seen.insert(key(link)) {
+            total += node.size * (links[&key(link)] - 1);
+        }
+    }
+    total + node
+        .children
+        .iter()
+        .map(|child| shared_bytes(child, links, seen))
+        .sum::<u64>()
+}
+
+// Walks in tree order, which is name order, so "first" is the same on every run
+fn apportion(
+    node: &mut FileInfo,
+    policy: HardLinkPolicy,
+    links: &HashMap<(u64, u64), u64>,
+    seen: &mut HashMap<(u64, u64), u64>,
+) {
+    if let Some(link) = node.hard_link {
+        let count = links[&key(link)];
+        if count > 1 {
+            // How many earlier links of this inode were already visited
+            let index = *seen.entry(key(link)).and_modify(|n| *n += 1).or_insert(0);
+            node.size = share(node.size, count, index, policy);
+            node.disk_size = share(node.disk_size, count, index, policy);
+        }
+        return;
+    }
+
+    if !node.is_dir {
+        return;
+    }
+
+    let own_disk_size = node.disk_size - node.children.iter().map(|child| child.disk_size).sum::<u64>();
+    for child in &mut node.children {
+        apportion(child, policy, links, seen);
+    }
+    node.size = node.children.iter().map(|child| child.size).sum();
+    node.disk_size = own_disk_size + node.children.iter().map(|child| child.disk_size).sum::<u64>();
+}
+
+// The `index`th of `count` links; with `Split`, the first link also takes the remainder
+fn share(size: u64, count: u64, index: u64, policy: HardLinkPolicy) -> u64 {
+    match policy {
+        HardLinkPolicy::First if index == 0 => size,
+        HardLinkPolicy::First => 0,
+        HardLinkPolicy::Split if index == 0 => size / count + size % count,
+        HardLinkPolicy::Split => size / count,
+    }
+}
+
+pub(crate) fn key(link: HardLink) -> (u64, u64) {
+    (link.dev, link.ino)
+}
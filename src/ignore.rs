@@ -0,0 +1,253 @@
+//! `.gitignore`, `.ignore` and global git excludes.
+//!
+//! Rules are read from every directory as the scan enters it. As in git, a
+//! pattern is relative to the directory holding its file, rules in deeper
+//! directories win over shallower ones, and within one file the last matching
+//! pattern wins. In a directory, `.ignore` takes precedence over `.gitignore`,
+//! which takes precedence over `.git/info/exclude`; the global excludes file
+//! (`core.excludesFile`, or `$XDG_CONFIG_HOME/git/ignore`) comes last.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::glob::Pattern;
+
+/// How ignore files shape the scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IgnoreFiles {
+    /// Ignore files are not read
+    #[default]
+    Disregard,
+    /// Ignored entries are left out, and ignored directories are never read
+    Respect,
+    /// Only ignored entries, and everything inside ignored directories, are counted
+    OnlyIgnored,
+}
+
+/// The rules in force for one directory, chained to those of its parent.
+#[derive(Debug)]
+pub(crate) struct IgnoreRules {
+    parent: Option<Arc<IgnoreRules>>,
+    /// Highest precedence first
+    sets: Vec<RuleSet>,
+}
+
+#[derive(Debug)]
+struct RuleSet {
+    /// Directory holding the rules, relative to the scan root
+    base: String,
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Rules for the scan root at `root`, including the global excludes.
+    pub(crate) fn for_root(root: &Path) -> Arc<IgnoreRules> {
+        let global = global_excludes_file()
+            .and_then(|path| read_patterns(&path))
+            .map(|patterns| RuleSet {
+                base: String::new(),
+                patterns,
+            });
+
+        let global = Arc::new(IgnoreRules {
+            parent: None,
+            sets: global.into_iter().collect(),
+        });
+        IgnoreRules::for_dir(&global, root, "")
+    }
+
+    /// Rules for the directory at `path`, `relative` to the scan root, below
+    /// a directory governed by `parent`.
+    pub(crate) fn for_dir(parent: &Arc<IgnoreRules>, path: &Path, relative: &str) -> Arc<IgnoreRules> {
+        let sets: Vec<RuleSet> = [".ignore", ".gitignore", ".git/info/exclude"]
+            .iter()
+            .filter_map(|file| read_patterns(&path.join(file)))
+            .map(|patterns| RuleSet {
+                base: relative.to_string(),
+                patterns,
+            })
+            .collect();
+
+        // Most directories have no rules of their own and share their parent's
+        if sets.is_empty() {
+            return Arc::clone(parent);
+        }
+
+        Arc::new(IgnoreRules {
+            parent: Some(Arc::clone(parent)),
+            sets,
+        })
+    }
+
+    /// Whether the entry at `relative` (from the scan root) is ignored.
+    pub(crate) fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        let mut rules = Some(self);
+
+        while let Some(current) = rules {
+            for set in &current.sets {
+                let path = if set.base.is_empty() {
+                    Some(relative)
+                } else {
+                    relative
+                        .strip_prefix(set.base.as_str())
+                        .and_then(|rest| rest.strip_prefix('/'))
+                };
+
+                let matched = path.and_then(|path| {
+                    set.patterns
+                        .iter()
+                        .rev()
+                        .find(|pattern| pattern.matches(path, is_dir))
+                });
+                if let Some(pattern) = matched {
+                    return !pattern.is_negated();
+                }
+            }
+            rules = current.parent.as_deref();
+        }
+
+        false
+    }
+}
+
+fn read_patterns(path: &Path) -> Option<Vec<Pattern>> {
+    let contents = fs::read_to_string(path).ok()?;
+    let patterns = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(Pattern::new)
+        .collect();
+    Some(patterns)
+}
+
+// `core.excludesFile` from the user's git config, else git's default location
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".config")));
+
+    let configs = [
+        config_home.as_ref().map(|dir| dir.join("git/config")),
+        home.as_ref().map(|home| home.join(".gitconfig")),
+    ];
+
+    // Later files win, as ~/.gitconfig overrides the XDG config in git
+    let mut configured = None;
+    for config in configs.iter().flatten() {
+        if let Some(value) = fs::read_to_string(config).ok().and_then(|text| excludes_file_setting(&text)) {
+            configured = Some(value);
+        }
+    }
+
+    match configured {
+        Some(value) => match (value.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(PathBuf::from(value)),
+        },
+        None => config_home.map(|dir| dir.join("git/ignore")),
+    }
+}
+
+// The value of `excludesfile` in the `[core]` section of a git config file
+fn excludes_file_setting(config: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut value = None;
+
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_core = line.trim_start_matches('[').trim_end_matches(']').trim().eq_ignore_ascii_case("core");
+        } else if in_core {
+            if let Some((key, setting)) = line.split_once('=') {
+                if key.trim().eq_ignore_ascii_case("excludesfile") {
+                    value = Some(setting.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, file: &str, contents: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    // Rules for `root` and its `sub` directory, with `global` standing in for
+    // the user's excludes file
+    fn rules(root: &Path, global: &str) -> (Arc<IgnoreRules>, Arc<IgnoreRules>) {
+        let global = Arc::new(IgnoreRules {
+            parent: None,
+            sets: vec![RuleSet {
+                base: String::new(),
+                patterns: global.lines().map(Pattern::new).collect(),
+            }],
+        });
+        let top = IgnoreRules::for_dir(&global, root, "");
+        let sub = IgnoreRules::for_dir(&top, &root.join("sub"), "sub");
+        (top, sub)
+    }
+
+    #[test]
+    fn deeper_files_and_later_patterns_win() {
+        let root = std::env::temp_dir().join(format!("sizetree-ignore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(&root, ".gitignore", "*.log\n/build\n# comment\n\ncache/\n");
+        write(&root, "sub/.gitignore", "!keep.log\n/out\n");
+
+        let (top, sub) = rules(&root, "");
+        assert!(top.is_ignored("app.log", false));
+        assert!(sub.is_ignored("sub/app.log", false));
+        assert!(!sub.is_ignored("sub/keep.log", false));
+        assert!(top.is_ignored("keep.log", false));
+
+        // Anchored patterns are relative to the directory holding them
+        assert!(top.is_ignored("build", true));
+        assert!(!sub.is_ignored("sub/build", true));
+        assert!(sub.is_ignored("sub/out", true));
+        assert!(!top.is_ignored("out", true));
+
+        assert!(sub.is_ignored("sub/cache", true));
+        assert!(!sub.is_ignored("sub/cache", false));
+        assert!(!top.is_ignored("readme", false));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn precedence_within_a_directory_and_global_excludes() {
+        let root = std::env::temp_dir().join(format!("sizetree-ignore-order-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(&root, ".git/info/exclude", "*.bak\nnotes\n");
+        write(&root, ".gitignore", "data\n!notes\n!kept.tmp\n");
+        write(&root, ".ignore", "!data\n");
+
+        let (top, _) = rules(&root, "*.tmp\n*.swp\n");
+        // .ignore beats .gitignore, which beats .git/info/exclude
+        assert!(!top.is_ignored("data", true));
+        assert!(!top.is_ignored("notes", false));
+        assert!(top.is_ignored("old.bak", false));
+        // The global excludes file comes last
+        assert!(top.is_ignored("a.swp", false));
+        assert!(top.is_ignored("a.tmp", false));
+        assert!(!top.is_ignored("kept.tmp", false));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reads_excludes_file_from_the_core_section() {
+        let config = "[user]\n\texcludesfile = wrong\n[core]\n\tExcludesFile = \"~/.gitignore_global\"\n";
+        assert_eq!(excludes_file_setting(config).as_deref(), Some("~/.gitignore_global"));
+        assert_eq!(excludes_file_setting("[core]\n\teditor = vi\n"), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! ncdu-style terminal browser over a scanned tree.
+//!
+//! The terminal is switched to raw mode with `stty` and drawn with plain ANSI
+//! escapes and ASCII, so it works in any VT100-compatible terminal down to 80x24.
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::render::{RenderOptions, SizeMeasure, SortOrder};
+use crate::tree::{FileInfo, SizeTree};
+
+const HELP: &str = "up/down move  right/enter open  left/bksp back  s size  n name  c count  q quit";
+const BAR_WIDTH: usize = 10;
+
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Open,
+    Back,
+    SortSize,
+    SortName,
+    SortCount,
+    Quit,
+    Other,
+}
+
+/// Browse `tree` until the user quits. `options.min_size` hides small entries
+/// and `options.sort` is the initial order; `max_depth` is not used.
+pub fn run(tree: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "interactive mode needs a terminal",
+        ));
+    }
+
+    let _terminal = RawTerminal::enter()?;
+    let mut browser = Browser {
+        path: vec![&tree.root],
+        selected: vec![0],
+        offset: 0,
+        options: options.clone(),
+    };
+
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let (rows, cols) = terminal_size();
+        stdout.write_all(browser.draw(rows, cols).as_bytes())?;
+        stdout.flush()?;
+
+        for key in read_keys(&mut stdin)? {
+            match key {
+                Key::Quit => return Ok(()),
+                key => browser.handle(key, rows.saturating_sub(3).max(1)),
+            }
+        }
+    }
+}
+
+struct Browser<'a> {
+    /// Directories from the root down to the one being shown
+    path: Vec<&'a FileInfo>,
+    /// Selected row in each directory of `path`
+    selected: Vec<usize>,
+    /// First visible row of the current directory
+    offset: usize,
+    options: RenderOptions,
+}
+
+impl<'a> Browser<'a> {
+    fn current(&self) -> &'a FileInfo {
+        self.path[self.path.len() - 1]
+    }
+
+    fn entries(&self) -> Vec<&'a FileInfo> {
+        self.options.visible_children(self.current())
+    }
+
+    fn selected_entry(&self) -> Option<&'a FileInfo> {
+        self.entries().get(*self.selected.last().unwrap()).copied()
+    }
+
+    fn handle(&mut self, key: Key, page: usize) {
+        let count = self.entries().len();
+        let selected = self.selected.last_mut().unwrap();
+
+        match key {
+            Key::Up => *selected = selected.saturating_sub(1),
+            Key::Down => *selected = (*selected + 1).min(count.saturating_sub(1)),
+            Key::PageUp => *selected = selected.saturating_sub(page),
+            Key::PageDown => *selected = (*selected + page).min(count.saturating_sub(1)),
+            Key::Home => *selected = 0,
+            Key::End => *selected = count.saturating_sub(1),
+            Key::Open => {
+                if let Some(entry) = self.selected_entry().filter(|entry| entry.is_dir) {
+                    self.path.push(entry);
+                    self.selected.push(0);
+                    self.offset = 0;
+                }
+            }
+            Key::Back => {
+                if self.path.len() > 1 {
+                    self.path.pop();
+                    self.selected.pop();
+                    self.offset = 0;
+                }
+            }
+            Key::SortSize => self.resort(SortOrder::Size),
+            Key::SortName => self.resort(SortOrder::Name),
+            Key::SortCount => self.resort(SortOrder::Count),
+            Key::Quit | Key::Other => {}
+        }
+    }
+
+    // Change the order everywhere while keeping each level on the same entry
+    fn resort(&mut self, sort: SortOrder) {
+        let previous: Vec<Option<&FileInfo>> = (0..self.path.len())
+            .map(|level| {
+                let entries = self.options.visible_children(self.path[level]);
+                entries.get(self.selected[level]).copied()
+            })
+            .collect();
+
+        self.options.sort = sort;
+
+        for (level, entry) in previous.into_iter().enumerate() {
+            let entries = self.options.visible_children(self.path[level]);
+            self.selected[level] = entry
+                .and_then(|entry| entries.iter().position(|other| std::ptr::eq(*other, entry)))
+                .unwrap_or(0);
+        }
+    }
+
+    fn draw(&mut self, rows: usize, cols: usize) -> String {
+        let current = self.current();
+        let entries = self.entries();
+        let selected = *self.selected.last().unwrap();
+        let height = rows.saturating_sub(3).max(1);
+
+        // Scroll just enough to keep the selection on screen
+        if selected < self.offset {
+            self.offset = selected;
+        } else if selected >= self.offset + height {
+            self.offset = selected + 1 - height;
+        }
+
+        let mut screen = String::from("\x1b[H");
+        let sort = match self.options.sort {
+            SortOrder::Size => "size",
+            SortOrder::Name => "name",
+            SortOrder::Count => "count",
+        };
+        let header = format!(
+            " {} ({}, {} items, by {})",
+            current.path.display(),
+            self.options.size_label(current),
+            entries.len(),
+            sort
+        );
+        push_line(&mut screen, &header, cols, true);
+
+        for row in 0..height {
+            match entries.get(self.offset + row) {
+                Some(entry) => {
+                    let line = entry_line(entry, current, &self.options);
+                    push_line(&mut screen, &line, cols, self.offset + row == selected);
+                }
+                None => push_line(&mut screen, "", cols, false),
+            }
+        }
+
+        let status = match entries.get(selected) {
+            Some(entry) => {
+                let target = match &entry.symlink_target {
+                    Some(target) => format!(" -> {}", target.display()),
+                    None => String::new(),
+                };
+                format!(
+                    " {}{} ({} bytes, {} on disk)",
+                    entry.path.display(),
+                    target,
+                    entry.size,
+                    entry.disk_size
+                )
+            }
+            None => " (empty)".to_string(),
+        };
+        push_line(&mut screen, &status, cols, false);
+
+        // The help line is last, without a line break that would scroll the screen
+        screen.push_str(&fit(HELP, cols));
+        screen.push_str("\x1b[K");
+        screen
+    }
+}
+
+fn entry_line(entry: &FileInfo, parent: &FileInfo, options: &RenderOptions) -> String {
+    let parent_size = options.size_of(parent);
+    let filled = if parent_size > 0 {
+        ((options.size_of(entry) as f64 / parent_size as f64) * BAR_WIDTH as f64).round() as usize
+    } else {
+        0
+    };
+    let filled = filled.min(BAR_WIDTH);
+    let suffix = if entry.is_dir {
+        "/"
+    } else if entry.symlink_target.is_some() {
+        "@"
+    } else {
+        ""
+    };
+
+    // The on-disk figure gets its own column when both are shown
+    let sizes = match options.measure {
+        SizeMeasure::Apparent => format!("{:>10}", options.format_size(entry.size)),
+        SizeMeasure::Disk => format!("{:>10}", options.format_size(entry.disk_size)),
+        SizeMeasure::Both => format!(
+            "{:>10} {:>10}",
+            options.format_size(entry.size),
+            options.format_size(entry.disk_size)
+        ),
+    };
+
+    // Counts get a column when asked for or when they decide the order
+    let counts = if options.show_counts || options.sort == SortOrder::Count {
+        format!(" {:>8}", entry.entry_count())
+    } else {
+        String::new()
+    };
+
+    // Totals under an unreadable path are only lower bounds
+    let note = if entry.partial { " [partial]" } else { "" };
+
+    format!(
+        " {}{} [{}{}] {}{}{}",
+        sizes,
+        counts,
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        entry.name(),
+        suffix,
+        note
+    )
+}
+
+fn push_line(screen: &mut String, text: &str, cols: usize, highlight: bool) {
+    let text = fit(text, cols);
+    if highlight {
+        let padding = cols.saturating_sub(text.chars().count());
+        screen.push_str(&format!("\x1b[7m{}{}\x1b[0m", text, " ".repeat(padding)));
+    } else {
+        screen.push_str(&text);
+    }
+    screen.push_str("\x1b[K\r\n");
+}
+
+fn fit(text: &str, cols: usize) -> String {
+    text.chars().take(cols).collect()
+}
+
+// Decode everything in one read, since held or pasted keys arrive together
+fn read_keys(stdin: &mut dyn Read) -> io::Result<Vec<Key>> {
+    let mut buf = [0u8; 64];
+    let n = stdin.read(&mut buf)?;
+    if n == 0 {
+        return Ok(vec![Key::Quit]);
+    }
+
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let len = if buf[i] == 0x1b && i + 2 < n && (buf[i + 1] == b'[' || buf[i + 1] == b'O') {
+            // Escape sequences end at the first byte in '@'..='~' after the prefix
+            buf[i + 2..n]
+                .iter()
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map_or(n - i, |end| end + 3)
+        } else {
+            1
+        };
+        keys.push(decode_key(&buf[i..i + len]));
+        i += len;
+    }
+
+    Ok(keys)
+}
+
+fn decode_key(bytes: &[u8]) -> Key {
+    match bytes {
+        b"q" | b"Q" | [0x1b] | [0x03] => Key::Quit,
+        b"\x1b[A" | b"\x1bOA" | b"k" => Key::Up,
+        b"\x1b[B" | b"\x1bOB" | b"j" => Key::Down,
+        b"\x1b[5~" => Key::PageUp,
+        b"\x1b[6~" | b" " => Key::PageDown,
+        b"\x1b[H" | b"\x1b[1~" | b"g" => Key::Home,
+        b"\x1b[F" | b"\x1b[4~" | b"G" => Key::End,
+        b"\x1b[C" | b"\x1bOC" | b"\r" | b"\n" | b"l" => Key::Open,
+        b"\x1b[D" | b"\x1bOD" | [0x7f] | [0x08] | b"h" => Key::Back,
+        b"s" => Key::SortSize,
+        b"n" => Key::SortName,
+        b"c" => Key::SortCount,
+        _ => Key::Other,
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(File::open("/dev/tty")?)
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!("stty {} failed", args.join(" "))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Rows and columns of the terminal, falling back to 24x80
+fn terminal_size() -> (usize, usize) {
+    let size = stty(&["size"]).unwrap_or_default();
+    let mut parts = size.split_whitespace().map(|part| part.parse::<usize>().ok());
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(rows), Some(cols)) if rows > 0 && cols > 0 => (rows, cols),
+        _ => (24, 80),
+    }
+}
+
+// Raw mode on the alternate screen, undone when dropped
+struct RawTerminal {
+    saved: String,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(RawTerminal { saved })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
@@ -0,0 +1,37 @@
+//! Scan a directory once into an in-memory size tree and render it.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use sizetree::{scan, ScanOptions};
+//!
+//! let tree = scan(Path::new("."), ScanOptions::default())?;
+//! println!("{} bytes", tree.root.size);
+//! # Ok::<(), sizetree::SizeError>(())
+//! ```
+
+pub mod age;
+pub mod breakdown;
+pub mod dupes;
+pub mod error;
+pub mod glob;
+pub mod hardlinks;
+pub mod ignore;
+pub mod interactive;
+pub mod owners;
+pub mod render;
+pub mod scan;
+pub mod size;
+pub mod snapshot;
+pub mod top;
+pub mod tree;
+
+pub use age::{format_age, parse_duration, TimeField};
+pub use breakdown::Breakdown;
+pub use error::{ScanError, SizeError};
+pub use glob::Pattern;
+pub use hardlinks::HardLinkPolicy;
+pub use ignore::IgnoreFiles;
+pub use scan::{scan, scan_streaming, ScanOptions, StreamEntry, SymlinkPolicy};
+pub use size::{format_size, format_size_in, parse_size, SizeUnits};
+pub use top::Largest;
+pub use tree::{FileInfo, HardLink, SizeTree, SkipReason};
@@ -1,14 +1,51 @@
-use std::fs;
-use std::io;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::env;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use clap::{Parser, ArgAction};
-use std::fmt;
+use std::process::ExitCode;
+use std::thread;
+use std::time::SystemTime;
+use clap::{Parser, Subcommand, ArgAction, ArgGroup, ValueEnum};
+use sizetree::breakdown::extension_of;
+use sizetree::dupes::find_duplicates;
+use sizetree::render::breakdown::write_breakdown;
+use sizetree::render::delimited::write_delimited;
+use sizetree::render::diff::write_diff;
+use sizetree::render::dupes::write_dupes;
+use sizetree::render::folded::write_folded;
+use sizetree::render::html::write_html;
+use sizetree::render::json::write_json;
+use sizetree::render::ndjson::write_ndjson_entry;
+use sizetree::render::svg::{write_svg, SvgOptions};
+use sizetree::render::text::write_text;
+use sizetree::render::top::write_top;
+use sizetree::render::{RenderOptions, SizeMeasure, SortOrder};
+use sizetree::interactive;
+use sizetree::owners::IdNames;
+use sizetree::snapshot::{read_snapshot, write_snapshot};
+use sizetree::{
+    parse_duration, parse_size, scan, scan_streaming, Breakdown, HardLinkPolicy, IgnoreFiles, Largest,
+    Pattern, ScanError, ScanOptions, SizeError, SizeUnits, StreamEntry, SymlinkPolicy, TimeField,
+};
+
+// Exit status when some paths could not be read, so the totals are lower bounds
+const EXIT_INCOMPLETE: u8 = 3;
 
 // Define command line arguments using clap
 #[derive(Parser, Debug)]
 #[command(name = "sizetree")]
 #[command(about = "Display directory sizes in a tree-like format", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(group(
+    ArgGroup::new("breakdown")
+        .args(["by_extension", "by_owner", "by_group"])
+        .conflicts_with_all(["top", "interactive", "format"])
+))]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Directory to analyze (defaults to current directory)
     #[arg(default_value = ".")]
     directory: PathBuf,
@@ -17,281 +54,681 @@ struct Args {
     #[arg(long, value_name = "N")]
     depth: Option<usize>,
 
-    /// Minimum size to display (e.g. 1MB, 500KB)
+    /// Minimum size to display (e.g. 1MiB, 500K, 2GB)
     #[arg(long, value_name = "SIZE", default_value = "0")]
     min_size: String,
 
     /// Sort by name instead of size
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "sort_count")]
     sort_name: bool,
-}
 
-struct FileInfo {
-    path: PathBuf,
-    size: u64,
-    is_dir: bool,
-}
+    /// Sort by the number of files and directories beneath each entry
+    #[arg(long, action = ArgAction::SetTrue)]
+    sort_count: bool,
+
+    /// Show file and directory counts next to sizes
+    #[arg(long, action = ArgAction::SetTrue)]
+    count: bool,
+
+    /// Show how long ago each entry, or anything beneath it, was last touched
+    #[arg(long, action = ArgAction::SetTrue)]
+    age: bool,
+
+    /// When to colour ages shown by --age: green within a month, yellow within a year, red beyond
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = Color::Auto)]
+    color: Color,
+
+    /// Show sizes in powers of 1000 (kB, MB, GB) instead of 1024 (KiB, MiB, GiB)
+    #[arg(long, action = ArgAction::SetTrue)]
+    si: bool,
+
+    /// Name the user owning the most bytes beneath each entry
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["interactive", "format", "top", "breakdown"])]
+    show_owner: bool,
+
+    /// Show allocated size on disk (st_blocks) instead of apparent size
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "both")]
+    disk_usage: bool,
+
+    /// Show apparent and on-disk size side by side
+    #[arg(long, action = ArgAction::SetTrue)]
+    both: bool,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// List the N largest files anywhere under the directory instead of the tree
+    #[arg(long, value_name = "N", conflicts_with_all = ["interactive", "format"])]
+    top: Option<usize>,
+
+    /// What `--top` ranks
+    #[arg(long, value_enum, value_name = "KIND", default_value_t = TopKind::Files, requires = "top")]
+    top_kind: TopKind,
+
+    /// Total bytes and file counts per file extension instead of the tree
+    #[arg(long, action = ArgAction::SetTrue)]
+    by_extension: bool,
+
+    /// Total bytes and file counts per owning user instead of the tree
+    #[arg(long, action = ArgAction::SetTrue)]
+    by_owner: bool,
 
-#[derive(Debug)]
-enum SizeError {
-    ParseError(String),
-    IoError(io::Error),
+    /// Total bytes and file counts per owning group instead of the tree
+    #[arg(long, action = ArgAction::SetTrue)]
+    by_group: bool,
+
+    /// Repeat the breakdown for each directory directly under the root
+    #[arg(long, action = ArgAction::SetTrue, requires = "breakdown")]
+    per_dir: bool,
+
+    /// Browse the scanned tree interactively instead of printing it
+    #[arg(short, long, action = ArgAction::SetTrue, conflicts_with = "format")]
+    interactive: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Width of the SVG treemap in pixels
+    #[arg(long, value_name = "PX", default_value_t = 1280)]
+    svg_width: u32,
+
+    /// Height of the SVG treemap in pixels
+    #[arg(long, value_name = "PX", default_value_t = 800)]
+    svg_height: u32,
 }
 
-impl fmt::Display for SizeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SizeError::ParseError(msg) => write!(f, "Size parsing error: {}", msg),
-            SizeError::IoError(err) => write!(f, "I/O error: {}", err),
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Scan a directory and save the tree for a later diff
+    Snapshot {
+        /// Directory to analyze (defaults to current directory)
+        #[arg(default_value = ".")]
+        directory: PathBuf,
+
+        /// Write the snapshot to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// Show what changed between two snapshots
+    Diff {
+        /// The earlier snapshot
+        old: PathBuf,
+
+        /// The later snapshot
+        new: PathBuf,
+
+        /// Maximum depth to display
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+
+        /// Hide entries that changed by less than this (e.g. 1MiB, 500K, 2GB)
+        #[arg(long, value_name = "SIZE", default_value = "0")]
+        min_size: String,
+
+        /// Compare allocated size on disk instead of apparent size
+        #[arg(long, action = ArgAction::SetTrue)]
+        disk_usage: bool,
+
+        /// Show sizes in powers of 1000 instead of 1024
+        #[arg(long, action = ArgAction::SetTrue)]
+        si: bool,
+    },
+    /// List files with identical contents and the space they waste
+    Dupes {
+        /// Directory to search (defaults to current directory)
+        #[arg(default_value = ".")]
+        directory: PathBuf,
+
+        /// Ignore files smaller than this (e.g. 1MiB, 500K, 2GB)
+        #[arg(long, value_name = "SIZE", default_value = "1")]
+        min_size: String,
+
+        /// Show sizes in powers of 1000 instead of 1024
+        #[arg(long, action = ArgAction::SetTrue)]
+        si: bool,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
 }
 
-impl From<io::Error> for SizeError {
-    fn from(error: io::Error) -> Self {
-        SizeError::IoError(error)
-    }
+// Options that decide what a scan counts, shared by every command that scans
+#[derive(clap::Args, Debug)]
+struct ScanArgs {
+    /// How to count a file reached through several hard links
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = HardLinks::First)]
+    hard_links: HardLinks,
+
+    /// Which symlinks to resolve instead of listing them as links
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = FollowSymlinks::Never)]
+    follow_symlinks: FollowSymlinks,
+
+    /// Don't descend into directories on other filesystems
+    #[arg(short = 'x', long, action = ArgAction::SetTrue)]
+    one_file_system: bool,
+
+    /// Leave out entries matching a gitignore-style glob (repeatable)
+    #[arg(long, value_name = "GLOB", action = ArgAction::Append)]
+    exclude: Vec<String>,
+
+    /// Only count files matching a gitignore-style glob (repeatable)
+    #[arg(long, value_name = "GLOB", action = ArgAction::Append)]
+    include: Vec<String>,
+
+    /// Leave out paths ignored by .gitignore, .ignore and git's excludes
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "only_ignored")]
+    gitignore: bool,
+
+    /// Only count paths ignored by .gitignore, .ignore and git's excludes
+    #[arg(long, action = ArgAction::SetTrue)]
+    only_ignored: bool,
+
+    /// How to report paths that could not be read
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = ErrorReport::Summary)]
+    errors: ErrorReport,
+
+    /// Only count files not touched for this long (e.g. 90d, 12h, 1y)
+    #[arg(long, value_name = "DURATION")]
+    older_than: Option<String>,
+
+    /// Only count files touched within this long (e.g. 7d, 30m)
+    #[arg(long, value_name = "DURATION")]
+    newer_than: Option<String>,
+
+    /// Which timestamp --age, --older-than and --newer-than use
+    #[arg(long, value_enum, value_name = "WHICH", default_value_t = Timestamp::Modified)]
+    time: Timestamp,
+
+    /// Number of threads used to scan (0 uses every available core); --top,
+    /// --by-* and --format ndjson always scan on one thread
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    threads: usize,
 }
 
-impl std::error::Error for SizeError {}
-
-fn get_size(path: &Path) -> Result<u64, SizeError> {
-    match fs::metadata(path) {
-        Ok(metadata) => {
-            if metadata.is_dir() {
-                let mut total_size = 0;
-                
-                // Read the directory silently ignoring errors
-                if let Ok(entries) = fs::read_dir(path) {
-                    for entry_result in entries {
-                        if let Ok(entry) = entry_result {
-                            // Silently ignore errors
-                            if let Ok(size) = get_size(&entry.path()) {
-                                total_size += size;
-                            }
-                        }
-                    }
+impl ScanArgs {
+    fn scan_options(&self) -> Result<ScanOptions, SizeError> {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+
+        // Durations become fixed instants, so every file is compared to the same cutoff
+        let now = SystemTime::now();
+        let cutoff = |duration: &Option<String>| -> Result<Option<SystemTime>, SizeError> {
+            match duration {
+                Some(duration) => {
+                    let duration = parse_duration(duration)?;
+                    Ok(Some(now.checked_sub(duration).unwrap_or(SystemTime::UNIX_EPOCH)))
                 }
-                Ok(total_size)
-            } else {
-                Ok(metadata.len())
+                None => Ok(None),
             }
-        },
-        Err(err) => Err(SizeError::IoError(err)) // Propagate the error without printing it
+        };
+
+        Ok(ScanOptions {
+            threads,
+            hard_links: match self.hard_links {
+                HardLinks::First => HardLinkPolicy::First,
+                HardLinks::Split => HardLinkPolicy::Split,
+            },
+            symlinks: match self.follow_symlinks {
+                FollowSymlinks::Never => SymlinkPolicy::Never,
+                FollowSymlinks::Roots => SymlinkPolicy::Roots,
+                FollowSymlinks::Always => SymlinkPolicy::Always,
+            },
+            one_file_system: self.one_file_system,
+            exclude: self.exclude.iter().map(|glob| Pattern::new(glob)).collect(),
+            include: self.include.iter().map(|glob| Pattern::new(glob)).collect(),
+            ignore_files: if self.gitignore {
+                IgnoreFiles::Respect
+            } else if self.only_ignored {
+                IgnoreFiles::OnlyIgnored
+            } else {
+                IgnoreFiles::Disregard
+            },
+            time_field: match self.time {
+                Timestamp::Modified => TimeField::Modified,
+                Timestamp::Accessed => TimeField::Accessed,
+                Timestamp::Changed => TimeField::Changed,
+            },
+            older_than: cutoff(&self.older_than)?,
+            newer_than: cutoff(&self.newer_than)?,
+        })
     }
 }
 
-fn format_size(size: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if size >= GB {
-        format!("{:.2} GB", size as f64 / GB as f64)
-    } else if size >= MB {
-        format!("{:.2} MB", size as f64 / MB as f64)
-    } else if size >= KB {
-        format!("{:.2} KB", size as f64 / KB as f64)
-    } else {
-        format!("{} B", size)
-    }
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HardLinks {
+    /// Count the whole size at the first path, in name order
+    First,
+    /// Split the size evenly across every link found (not with streamed output)
+    Split,
 }
 
-fn parse_size(size_str: &str) -> Result<u64, SizeError> {
-    let size_str = size_str.trim().to_uppercase();
-    
-    if size_str.is_empty() {
-        return Err(SizeError::ParseError("Empty size string".to_string()));
-    }
-    
-    let (num_str, unit) = if size_str.ends_with("KB") {
-        (&size_str[..size_str.len() - 2], "KB")
-    } else if size_str.ends_with("MB") {
-        (&size_str[..size_str.len() - 2], "MB")
-    } else if size_str.ends_with("GB") {
-        (&size_str[..size_str.len() - 2], "GB")
-    } else if size_str.ends_with("B") {
-        (&size_str[..size_str.len() - 1], "B")
-    } else if size_str.ends_with("K") {
-        (&size_str[..size_str.len() - 1], "KB")
-    } else if size_str.ends_with("M") {
-        (&size_str[..size_str.len() - 1], "MB")
-    } else if size_str.ends_with("G") {
-        (&size_str[..size_str.len() - 1], "GB")
-    } else {
-        (size_str.as_str(), "B")
-    };
-    
-    let num = num_str.parse::<f64>()
-        .map_err(|_| SizeError::ParseError(format!("Invalid number: {}", num_str)))?;
-    
-    let multiplier = match unit {
-        "KB" => 1024,
-        "MB" => 1024 * 1024,
-        "GB" => 1024 * 1024 * 1024,
-        "B" => 1,
-        _ => return Err(SizeError::ParseError(format!("Unknown unit: {}", unit))),
-    };
-    
-    Ok((num * multiplier as f64) as u64)
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FollowSymlinks {
+    /// List every symlink as a link, including the directory argument
+    Never,
+    /// Resolve the directory argument if it is a symlink
+    Roots,
+    /// Resolve every symlink, skipping parent directories and directories already counted
+    Always,
 }
 
-fn walk_dir(
-    dir: &Path,
-    prefix: &str,
-    max_depth: Option<usize>,
-    min_size: u64,
-    sort_by_size: bool,
-    current_depth: usize,
-) -> Result<(), SizeError> {
-    if let Some(max_depth) = max_depth {
-        if current_depth > max_depth {
-            return Ok(());
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ErrorReport {
+    /// Say nothing (the exit status still reflects them)
+    Ignore,
+    /// Print how many paths could not be read, grouped by cause
+    Summary,
+    /// Print every path that could not be read
+    Verbose,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Timestamp {
+    /// Last modification (mtime)
+    #[value(alias = "mtime")]
+    Modified,
+    /// Last access (atime)
+    #[value(alias = "atime")]
+    Accessed,
+    /// Last content or metadata change (ctime)
+    #[value(alias = "ctime")]
+    Changed,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Color {
+    /// Colour when writing to a terminal and NO_COLOR is not set
+    Auto,
+    /// Never colour
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum TopKind {
+    /// Files and symlinks only
+    Files,
+    /// Directories only
+    Dirs,
+    /// Files and directories ranked together
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    /// Indented tree with icons
+    Text,
+    /// Nested JSON document with exact byte sizes
+    Json,
+    /// One JSON object per entry, streamed while scanning on one thread
+    Ndjson,
+    /// Comma-separated rows, one per entry
+    Csv,
+    /// Tab-separated rows, one per entry
+    Tsv,
+    /// Self-contained interactive HTML report
+    Html,
+    /// Squarified treemap as an SVG image
+    Svg,
+    /// Folded stacks (`a;b;c <bytes>`) for flamegraph tools
+    Folded,
+}
+
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // Parse arguments using clap
+    let args = Args::parse();
+
+    match &args.command {
+        Some(Command::Snapshot { directory, output, scan }) => {
+            return snapshot(directory, output.as_deref(), scan);
+        }
+        Some(Command::Dupes {
+            directory,
+            min_size,
+            si,
+            scan,
+        }) => {
+            let render_options = RenderOptions {
+                units: units(*si),
+                ..RenderOptions::default()
+            };
+            return dupes(directory, parse_size(min_size)?, scan, &render_options);
+        }
+        Some(Command::Diff {
+            old,
+            new,
+            depth,
+            min_size,
+            disk_usage,
+            si,
+        }) => {
+            let render_options = RenderOptions {
+                max_depth: *depth,
+                min_size: parse_size(min_size)?,
+                measure: if *disk_usage {
+                    SizeMeasure::Disk
+                } else {
+                    SizeMeasure::Apparent
+                },
+                units: units(*si),
+                ..RenderOptions::default()
+            };
+            let old = read_snapshot(&mut BufReader::new(File::open(old)?))?;
+            let new = read_snapshot(&mut BufReader::new(File::open(new)?))?;
+
+            let mut out = io::stdout().lock();
+            write_diff(&mut out, &old, &new, &render_options)?;
+            out.flush()?;
+            return Ok(ExitCode::SUCCESS);
         }
+        None => {}
     }
 
-    // Read directory, ignoring errors
-    let entries = match fs::read_dir(dir) {
-        Ok(entries) => {
-            let mut entry_vec = Vec::new();
-            for entry_result in entries {
-                if let Ok(entry) = entry_result {
-                    entry_vec.push(entry);
-                }
-                // Silently ignore entries with errors
-            }
-            entry_vec
+    // Parse the minimum size
+    let min_size = parse_size(&args.min_size)?;
+    
+    let dir = &args.directory;
+    check_directory(dir, &args.scan)?;
+
+    let scan_options = args.scan.scan_options()?;
+
+    // Streamed modes report each file before the later links to it are found
+    let streamed = args.top.is_some()
+        || args.by_extension
+        || args.by_owner
+        || args.by_group
+        || matches!(args.format, OutputFormat::Ndjson);
+    if streamed && scan_options.hard_links == HardLinkPolicy::Split {
+        return Err("Error: --hard-links split can't be used with --top, --by-extension, --by-owner, \
+            --by-group or --format ndjson"
+            .into());
+    }
+    // They walk the tree serially, so more threads would be silently unused
+    if streamed && args.scan.threads != 1 {
+        return Err("Error: --threads can't be used with --top, --by-extension, --by-owner, \
+            --by-group or --format ndjson, which scan on one thread"
+            .into());
+    }
+
+    let render_options = RenderOptions {
+        max_depth: args.depth,
+        min_size,
+        sort: if args.sort_name {
+            SortOrder::Name
+        } else if args.sort_count {
+            SortOrder::Count
+        } else {
+            SortOrder::Size
         },
-        Err(err) => return Err(SizeError::IoError(err)), // Only propagate the main error
+        measure: if args.disk_usage {
+            SizeMeasure::Disk
+        } else if args.both {
+            SizeMeasure::Both
+        } else {
+            SizeMeasure::Apparent
+        },
+        show_counts: args.count,
+        show_owner: args.show_owner,
+        show_age: args.age,
+        // NO_COLOR is the common way to turn colour off everywhere at once
+        color: match args.color {
+            Color::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            Color::Never => false,
+        },
+        units: units(args.si),
     };
 
-    let mut files = Vec::new();
-
-    // Collect all entries
-    for entry in &entries {
-        let path = entry.path();
-        
-        // Ignore files we can't access
-        let metadata = match fs::metadata(&path) {
-            Ok(meta) => meta,
-            Err(_) => continue, // Silently skip this file
-        };
-        
-        let is_dir = metadata.is_dir();
-        let size = if is_dir {
-            match get_size(&path) {
-                Ok(s) => s,
-                Err(_) => 0, // Use 0 as size for directories with errors
+    // The largest entries are picked while streaming, so the tree is never held in memory
+    if let Some(limit) = args.top {
+        let mut largest = Largest::new(limit);
+        let errors = scan_streaming(dir, scan_options, &mut |entry| {
+            let wanted = match args.top_kind {
+                TopKind::Files => !entry.is_dir,
+                TopKind::Dirs => entry.is_dir,
+                TopKind::All => true,
+            };
+            let size = render_options.measured(entry.size, entry.disk_size);
+            // The root would top every directory listing, so it is left out
+            if wanted && entry.depth > 0 && size >= render_options.min_size {
+                largest.offer(size, entry.path);
             }
-        } else {
-            metadata.len()
+            Ok(())
+        })?;
+
+        let what = match args.top_kind {
+            TopKind::Files => "files",
+            TopKind::Dirs => "directories",
+            TopKind::All => "entries",
         };
+        let mut out = io::stdout().lock();
+        write_top(&mut out, dir, &largest.into_sorted_vec(), what, &render_options)?;
+        out.flush()?;
+        return Ok(report_errors(&errors, args.scan.errors));
+    }
 
-        if size < min_size {
-            continue;
-        }
+    // Breakdowns only need running totals, so they are also built while streaming
+    type KeyOf = Box<dyn Fn(&StreamEntry) -> String>;
+    let breakdown: Option<(&str, KeyOf)> = if args.by_extension {
+        Some(("Extension", Box::new(|entry| extension_of(entry.path))))
+    } else if args.by_owner {
+        let users = IdNames::users();
+        Some(("Owner", Box::new(move |entry| users.name(entry.uid))))
+    } else if args.by_group {
+        let groups = IdNames::groups();
+        Some(("Group", Box::new(move |entry| groups.name(entry.gid))))
+    } else {
+        None
+    };
 
-        files.push(FileInfo {
-            path,
-            size,
-            is_dir,
-        });
+    if let Some((column, key_of)) = breakdown {
+        let errors = write_grouped(dir, scan_options, &render_options, args.per_dir, column, &key_of)?;
+        return Ok(report_errors(&errors, args.scan.errors));
     }
 
-    // Sort files by size or name as appropriate
-    if sort_by_size {
-        files.sort_by(|a, b| b.size.cmp(&a.size));
-    } else {
-        files.sort_by(|a, b| {
-            let a_name = a.path.file_name().unwrap_or_default().to_string_lossy();
-            let b_name = b.path.file_name().unwrap_or_default().to_string_lossy();
-            a_name.cmp(&b_name)
-        });
+    // Streamed formats write entries while the scan is still running
+    if let OutputFormat::Ndjson = args.format {
+        let mut out = io::stdout().lock();
+        let errors = scan_streaming(dir, scan_options, &mut |entry| {
+            write_ndjson_entry(&mut out, entry, &render_options)
+        })?;
+        out.flush()?;
+        return Ok(report_errors(&errors, args.scan.errors));
     }
 
-    let total_entries = files.len();
-    for (i, file) in files.iter().enumerate() {
-        let is_last_entry = i == total_entries - 1;
-        let file_name = file.path.file_name().unwrap_or_default().to_string_lossy();
-        
-        // Choose an icon based on file type
-        let icon = if file.is_dir { "📂" } else { "📄" };
+    // Scan the whole tree once, then render it from memory
+    let tree = scan(dir, scan_options)?;
 
-        let connector = if is_last_entry {
-            "└── "
-        } else {
-            "├── "
-        };
+    if args.interactive {
+        interactive::run(&tree, &render_options)?;
+        return Ok(report_errors(&tree.errors, args.scan.errors));
+    }
 
-        // Print the entry with an icon
-        println!(
-            "{}{}{} {} ({})",
-            prefix,
-            connector,
-            icon,
-            file_name,
-            format_size(file.size)
-        );
-
-        // Recurse into directories
-        if file.is_dir {
-            let new_prefix = if is_last_entry {
-                format!("{}    ", prefix)
-            } else {
-                format!("{}│   ", prefix)
+    let mut out = io::stdout().lock();
+
+    match args.format {
+        OutputFormat::Text => write_text(&mut out, &tree, &render_options)?,
+        OutputFormat::Json => write_json(&mut out, &tree, &render_options)?,
+        OutputFormat::Csv => write_delimited(&mut out, &tree, &render_options, ',')?,
+        OutputFormat::Tsv => write_delimited(&mut out, &tree, &render_options, '\t')?,
+        OutputFormat::Html => write_html(&mut out, &tree, &render_options)?,
+        OutputFormat::Svg => {
+            let svg_options = SvgOptions {
+                width: args.svg_width,
+                height: args.svg_height,
             };
+            write_svg(&mut out, &tree, &render_options, &svg_options)?
+        }
+        OutputFormat::Folded => write_folded(&mut out, &tree, &render_options)?,
+        OutputFormat::Ndjson => unreachable!(),
+    }
+    out.flush()?;
 
-            // Silently ignore errors in recursion
-            let _ = walk_dir(
-                &file.path, 
-                &new_prefix, 
-                max_depth, 
-                min_size, 
-                sort_by_size, 
-                current_depth + 1
-            );
+    Ok(report_errors(&tree.errors, args.scan.errors))
+}
+
+// Stream the scan into totals per key, overall and optionally per directory
+// directly under the root, then print them as tables
+fn write_grouped(
+    dir: &Path,
+    scan_options: ScanOptions,
+    render_options: &RenderOptions,
+    per_dir: bool,
+    column: &str,
+    key_of: &dyn Fn(&StreamEntry) -> String,
+) -> Result<Vec<ScanError>, Box<dyn std::error::Error>> {
+    let mut overall = Breakdown::default();
+    let mut by_dir: BTreeMap<PathBuf, Breakdown> = BTreeMap::new();
+
+    let errors = scan_streaming(dir, scan_options, &mut |entry| {
+        if entry.is_dir {
+            return Ok(());
+        }
+        let key = key_of(entry);
+        overall.add(&key, entry.size, entry.disk_size);
+
+        if per_dir {
+            // Files directly in the root are grouped under the root itself
+            let top = match entry.path.strip_prefix(dir).ok().and_then(|rel| rel.components().next()) {
+                Some(first) if entry.depth > 1 => dir.join(first),
+                _ => dir.to_path_buf(),
+            };
+            by_dir.entry(top).or_default().add(&key, entry.size, entry.disk_size);
         }
+        Ok(())
+    })?;
+
+    let mut out = io::stdout().lock();
+    write_breakdown(&mut out, column, &overall, render_options)?;
+    for (path, breakdown) in &by_dir {
+        writeln!(out)?;
+        writeln!(out, "{}:", path.display())?;
+        write_breakdown(&mut out, column, breakdown, render_options)?;
     }
+    out.flush()?;
 
-    Ok(())
+    Ok(errors)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Parse arguments using clap
-    let args = Args::parse();
-    
-    // Parse the minimum size
-    let min_size = parse_size(&args.min_size)?;
-    
-    let dir = &args.directory;
-    
-    // Verify that the directory is valid
+// Scan `dir`, then hash only the files that share a size with another
+fn dupes(
+    dir: &Path,
+    min_size: u64,
+    scan_args: &ScanArgs,
+    render_options: &RenderOptions,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    check_directory(dir, scan_args)?;
+    let scan_options = ScanOptions {
+        // The first link to each inode must keep its full size to be compared
+        hard_links: HardLinkPolicy::First,
+        ..scan_args.scan_options()?
+    };
+    let mut tree = scan(dir, scan_options)?;
+
+    let (sets, errors) = find_duplicates(&tree.root, min_size);
+    tree.errors.extend(errors);
+
+    let mut out = io::stdout().lock();
+    write_dupes(&mut out, dir, &sets, render_options)?;
+    out.flush()?;
+
+    Ok(report_errors(&tree.errors, scan_args.errors))
+}
+
+// Scan `dir` and save the whole tree, unfiltered, for `diff`
+fn snapshot(dir: &Path, output: Option<&Path>, scan_args: &ScanArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    check_directory(dir, scan_args)?;
+    let tree = scan(dir, scan_args.scan_options()?)?;
+
+    match output {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_snapshot(&mut out, &tree)?;
+            out.flush()?;
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            write_snapshot(&mut out, &tree)?;
+            out.flush()?;
+        }
+    }
+
+    Ok(report_errors(&tree.errors, scan_args.errors))
+}
+
+fn units(si: bool) -> SizeUnits {
+    if si {
+        SizeUnits::Si
+    } else {
+        SizeUnits::Iec
+    }
+}
+
+// Verify that the directory is valid
+fn check_directory(dir: &Path, scan_args: &ScanArgs) -> Result<(), Box<dyn std::error::Error>> {
     if !dir.exists() {
         return Err(format!("Error: {} does not exist", dir.display()).into());
     }
-    
+
     if !dir.is_dir() {
         return Err(format!("Error: {} is not a directory", dir.display()).into());
     }
-    
-    // Get and display the root directory size
-    match get_size(dir) {
-        Ok(root_size) => {
-            println!("{} ({})", dir.display(), format_size(root_size));
-            
-            // Skip displaying the tree if the root directory is smaller than min_size
-            if root_size < min_size {
-                println!("No entries meet the minimum size criteria.");
-                return Ok(());
-            }
-            
-            // Display the tree and silently ignore errors
-            let _ = walk_dir(dir, "", args.depth, min_size, !args.sort_name, 0);
-        },
-        Err(err) => {
-            return Err(Box::new(err));
+
+    // The directory exists through the link, but only the link itself will be counted
+    if let FollowSymlinks::Never = scan_args.follow_symlinks {
+        if dir.is_symlink() {
+            eprintln!(
+                "Note: {} is a symlink and is listed as one; use --follow-symlinks roots to scan its target",
+                dir.display()
+            );
         }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+// Describe unreadable paths on stderr and pick the exit status
+fn report_errors(errors: &[ScanError], report: ErrorReport) -> ExitCode {
+    if errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    match report {
+        ErrorReport::Ignore => {}
+        ErrorReport::Summary => {
+            let mut causes: Vec<(String, usize)> = Vec::new();
+            for error in errors {
+                let cause = error.kind.to_string();
+                match causes.iter_mut().find(|(known, _)| *known == cause) {
+                    Some((_, count)) => *count += 1,
+                    None => causes.push((cause, 1)),
+                }
+            }
+            let causes: Vec<String> = causes
+                .iter()
+                .map(|(cause, count)| format!("{} {}", count, cause))
+                .collect();
+            eprintln!(
+                "Warning: {} path(s) could not be read ({}); totals marked [partial] are incomplete. \
+                 Use --errors=verbose to list them.",
+                errors.len(),
+                causes.join(", ")
+            );
+        }
+        ErrorReport::Verbose => {
+            for error in errors {
+                eprintln!("Error: {}", error);
+            }
+            eprintln!(
+                "Warning: {} path(s) could not be read; totals marked [partial] are incomplete.",
+                errors.len()
+            );
+        }
+    }
+
+    ExitCode::from(EXIT_INCOMPLETE)
+}
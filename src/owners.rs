@@ -0,0 +1,93 @@
+//! User and group names for the ids recorded by a scan, and who owns the most
+//! bytes under each directory.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::render::RenderOptions;
+use crate::tree::FileInfo;
+
+/// Names for numeric user or group ids, read from a file in `/etc/passwd`
+/// format (`name:password:id:...`). Ids without a name are shown as numbers.
+#[derive(Debug, Clone, Default)]
+pub struct IdNames {
+    names: HashMap<u32, String>,
+}
+
+impl IdNames {
+    /// User names from `/etc/passwd`, or none if it can't be read.
+    pub fn users() -> Self {
+        Self::load(Path::new("/etc/passwd"))
+    }
+
+    /// Group names from `/etc/group`, or none if it can't be read.
+    pub fn groups() -> Self {
+        Self::load(Path::new("/etc/group"))
+    }
+
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Read `name:password:id` lines; the first name given for an id wins.
+    pub fn parse(text: &str) -> Self {
+        let mut names = HashMap::new();
+        for line in text.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split(':');
+            let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if let Ok(id) = id.trim().parse::<u32>() {
+                names.entry(id).or_insert_with(|| name.to_string());
+            }
+        }
+        IdNames { names }
+    }
+
+    /// The name for `id`, or the id itself when it has none.
+    pub fn name(&self, id: u32) -> String {
+        match self.names.get(&id) {
+            Some(name) => name.clone(),
+            None => id.to_string(),
+        }
+    }
+}
+
+/// For every node under `root`, the user owning the most bytes of files in
+/// or below it under the selected measure. Ties go to the lower uid, and a
+/// directory with no files is attributed to its own owner.
+pub fn top_owners<'a>(root: &'a FileInfo, options: &RenderOptions) -> HashMap<&'a Path, u32> {
+    let mut top = HashMap::new();
+    bytes_by_owner(root, options, &mut top);
+    top
+}
+
+fn bytes_by_owner<'a>(
+    node: &'a FileInfo,
+    options: &RenderOptions,
+    top: &mut HashMap<&'a Path, u32>,
+) -> HashMap<u32, u64> {
+    let mut bytes = HashMap::new();
+    if node.is_dir {
+        for child in &node.children {
+            for (uid, size) in bytes_by_owner(child, options, top) {
+                *bytes.entry(uid).or_insert(0) += size;
+            }
+        }
+    } else {
+        bytes.insert(node.uid, options.size_of(node));
+    }
+
+    let owner = bytes
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map_or(node.uid, |(uid, _)| *uid);
+    top.insert(node.path.as_path(), owner);
+    bytes
+}
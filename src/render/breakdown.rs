@@ -0,0 +1,54 @@
+//! Grouped totals as an aligned table with a share of the whole and a total row.
+
+use std::io::{self, Write};
+
+use crate::breakdown::Breakdown;
+use crate::render::{RenderOptions, SizeMeasure};
+
+/// Write one row per group in `breakdown`, headed by `column`, then a total row.
+pub fn write_breakdown(
+    out: &mut dyn Write,
+    column: &str,
+    breakdown: &Breakdown,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let rows = breakdown.rows(options);
+    let total = breakdown.total();
+    let total_size = total.size_under(options);
+
+    let size_heading = match options.measure {
+        SizeMeasure::Disk => "On disk",
+        SizeMeasure::Apparent | SizeMeasure::Both => "Size",
+    };
+    let width = rows
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .chain([column.len(), "Total".len()])
+        .max()
+        .unwrap_or(0);
+
+    writeln!(out, "{:<width$}  {:>8}  {:>10}  {:>6}", column, "Files", size_heading, "Share")?;
+    for (key, totals) in &rows {
+        let size = totals.size_under(options);
+        let share = if total_size > 0 {
+            size as f64 * 100.0 / total_size as f64
+        } else {
+            0.0
+        };
+        writeln!(
+            out,
+            "{:<width$}  {:>8}  {:>10}  {:>5.1}%",
+            key,
+            totals.files,
+            options.format_size(size),
+            share
+        )?;
+    }
+    writeln!(
+        out,
+        "{:<width$}  {:>8}  {:>10}",
+        "Total",
+        total.files,
+        options.format_size(total_size)
+    )
+}
@@ -0,0 +1,77 @@
+//! CSV and TSV export, one row per entry.
+//!
+//! Columns are `path`, `depth` (0 for the root), `type` (`dir`, `file` or `symlink`),
+//! `size` (apparent bytes), `human_size`, `entries` (the number of files and
+//! directories anywhere beneath the entry), then `disk_size` (allocated bytes),
+//! `human_disk_size`, `files` and `dirs`, which split `entries` by type, and
+//! `partial` (`true` when something in or below the entry could not be read,
+//! so its totals are lower bounds). Fields containing the delimiter, a
+//! quote or a line break are quoted as in RFC 4180, for TSV as well as CSV.
+
+use std::io::{self, Write};
+
+use crate::render::RenderOptions;
+use crate::tree::{FileInfo, SizeTree};
+
+/// Write a header and one row per entry shown under `options`, separated by `delimiter`.
+pub fn write_delimited(
+    out: &mut dyn Write,
+    tree: &SizeTree,
+    options: &RenderOptions,
+    delimiter: char,
+) -> io::Result<()> {
+    let header = [
+        "path",
+        "depth",
+        "type",
+        "size",
+        "human_size",
+        "entries",
+        "disk_size",
+        "human_disk_size",
+        "files",
+        "dirs",
+        "partial",
+    ];
+    writeln!(out, "{}", header.join(&delimiter.to_string()))?;
+    write_rows(out, &tree.root, options, delimiter, 0)
+}
+
+fn write_rows(
+    out: &mut dyn Write,
+    node: &FileInfo,
+    options: &RenderOptions,
+    delimiter: char,
+    depth: usize,
+) -> io::Result<()> {
+    let fields = [
+        quote(&node.path.to_string_lossy(), delimiter),
+        depth.to_string(),
+        node.kind().to_string(),
+        node.size.to_string(),
+        options.format_size(node.size),
+        node.entry_count().to_string(),
+        node.disk_size.to_string(),
+        options.format_size(node.disk_size),
+        node.files.to_string(),
+        node.dirs.to_string(),
+        node.partial.to_string(),
+    ];
+    writeln!(out, "{}", fields.join(&delimiter.to_string()))?;
+
+    if options.shows_depth(depth) {
+        for child in options.visible_children(node) {
+            write_rows(out, child, options, delimiter, depth + 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
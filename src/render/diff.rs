@@ -0,0 +1,167 @@
+//! Changes between two scans of the same directory, as an indented tree.
+//!
+//! Entries are matched by name level by level. Only entries whose size changed,
+//! or that were added or removed, are shown, largest change first. Each line
+//! carries the signed change and, for entries present in both scans, the change
+//! as a percentage of the old size; `[added]` and `[removed]` mark entries found
+//! in only one of them. `--min-size` applies to the size of the change.
+
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use crate::render::RenderOptions;
+use crate::tree::{FileInfo, SizeTree};
+
+// One entry that differs between the scans, with its differing children
+struct Change<'a> {
+    old: Option<&'a FileInfo>,
+    new: Option<&'a FileInfo>,
+    delta: i128,
+    children: Vec<Change<'a>>,
+}
+
+impl<'a> Change<'a> {
+    // Whichever side exists, preferring the new one
+    fn node(&self) -> &'a FileInfo {
+        self.new.or(self.old).unwrap()
+    }
+}
+
+/// Write the entries that changed between `old` and `new`.
+pub fn write_diff(out: &mut dyn Write, old: &SizeTree, new: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    let root = compare(Some(&old.root), Some(&new.root), options);
+    let old_size = options.size_of(&old.root);
+    let new_size = options.size_of(&new.root);
+
+    writeln!(
+        out,
+        "{} ({}: {} -> {})",
+        new.root.path.display(),
+        change_label(&root, options),
+        options.format_size(old_size),
+        options.format_size(new_size)
+    )?;
+
+    // Decided after filtering, so changes hidden by --min-size or --depth are explained
+    if write_changes(out, &root, "", options, 0)? == 0 {
+        if root.children.is_empty() {
+            writeln!(out, "No changes.")?;
+        } else {
+            writeln!(out, "No changes meet the minimum size criteria.")?;
+        }
+    }
+    Ok(())
+}
+
+fn compare<'a>(old: Option<&'a FileInfo>, new: Option<&'a FileInfo>, options: &RenderOptions) -> Change<'a> {
+    let old_size = old.map_or(0, |node| options.size_of(node)) as i128;
+    let new_size = new.map_or(0, |node| options.size_of(node)) as i128;
+
+    // Both child lists are in name order, so they can be merged in one pass
+    let old_children: &[FileInfo] = old.map_or(&[], |node| &node.children);
+    let new_children: &[FileInfo] = new.map_or(&[], |node| &node.children);
+    let (mut i, mut j) = (0, 0);
+    let mut children = Vec::new();
+    while i < old_children.len() || j < new_children.len() {
+        let (old_child, new_child) = match (old_children.get(i), new_children.get(j)) {
+            (Some(a), Some(b)) if a.path.file_name() == b.path.file_name() => (Some(a), Some(b)),
+            (Some(a), Some(b)) if a.path.file_name() < b.path.file_name() => (Some(a), None),
+            (Some(_), Some(b)) => (None, Some(b)),
+            (Some(a), None) => (Some(a), None),
+            (None, b) => (None, b),
+        };
+        i += usize::from(old_child.is_some());
+        j += usize::from(new_child.is_some());
+
+        let child = compare(old_child, new_child, options);
+        if child.delta != 0 || child.old.is_none() || child.new.is_none() || !child.children.is_empty() {
+            children.push(child);
+        }
+    }
+
+    Change {
+        old,
+        new,
+        delta: new_size - old_size,
+        children,
+    }
+}
+
+// The signed change, with a percentage when there is an old size to compare to
+fn change_label(change: &Change, options: &RenderOptions) -> String {
+    let sign = match change.delta {
+        d if d > 0 => "+",
+        d if d < 0 => "-",
+        _ => "",
+    };
+    let label = format!("{}{}", sign, options.format_size(change.delta.unsigned_abs() as u64));
+
+    match change.old.map(|node| options.size_of(node)) {
+        Some(old_size) if old_size > 0 && change.new.is_some() => {
+            format!("{}, {:+.1}%", label, change.delta as f64 * 100.0 / old_size as f64)
+        }
+        _ => label,
+    }
+}
+
+// Write the changes below `change` that pass the filters, returning how many lines that took
+fn write_changes(
+    out: &mut dyn Write,
+    change: &Change,
+    prefix: &str,
+    options: &RenderOptions,
+    current_depth: usize,
+) -> io::Result<usize> {
+    if !options.shows_depth(current_depth) {
+        return Ok(0);
+    }
+
+    // Largest change first; the merge left ties in name order
+    let mut children: Vec<&Change> = change
+        .children
+        .iter()
+        .filter(|child| child.delta.unsigned_abs() >= options.min_size as u128)
+        .collect();
+    children.sort_by_key(|child| Reverse(child.delta.unsigned_abs()));
+
+    let total_entries = children.len();
+    let mut written = 0;
+    for (i, child) in children.iter().enumerate() {
+        let is_last_entry = i == total_entries - 1;
+        let node = child.node();
+
+        let icon = if node.symlink_target.is_some() {
+            "🔗"
+        } else if node.is_dir {
+            "📂"
+        } else {
+            "📄"
+        };
+        let connector = if is_last_entry { "└── " } else { "├── " };
+        let marker = match (child.old, child.new) {
+            (None, _) => " [added]",
+            (_, None) => " [removed]",
+            _ => "",
+        };
+
+        writeln!(
+            out,
+            "{}{}{} {} ({}){}",
+            prefix,
+            connector,
+            icon,
+            node.name(),
+            change_label(child, options),
+            marker
+        )?;
+
+        let new_prefix = if is_last_entry {
+            format!("{}    ", prefix)
+        } else {
+            format!("{}│   ", prefix)
+        };
+        written += 1 + write_changes(out, child, &new_prefix, options, current_depth + 1)?;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,46 @@
+//! The `dupes` report: each set of identical files with the bytes it wastes,
+//! then a total.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::dupes::DuplicateSet;
+use crate::render::RenderOptions;
+
+/// Write every set in `sets`, with paths relative to `root`, and a summary line.
+pub fn write_dupes(
+    out: &mut dyn Write,
+    root: &Path,
+    sets: &[DuplicateSet],
+    options: &RenderOptions,
+) -> io::Result<()> {
+    if sets.is_empty() {
+        writeln!(out, "No duplicate files under {}.", root.display())?;
+        return Ok(());
+    }
+
+    for set in sets {
+        writeln!(
+            out,
+            "{} copies of {} ({} wasted):",
+            set.paths.len(),
+            options.format_size(set.size),
+            options.format_size(set.wasted())
+        )?;
+        for path in &set.paths {
+            writeln!(out, "  {}", path.strip_prefix(root).unwrap_or(path).display())?;
+        }
+        writeln!(out)?;
+    }
+
+    let files: usize = sets.iter().map(|set| set.paths.len()).sum();
+    let wasted: u64 = sets.iter().map(DuplicateSet::wasted).sum();
+    writeln!(
+        out,
+        "{} duplicate {} of {} files, {} wasted",
+        sets.len(),
+        if sets.len() == 1 { "set" } else { "sets" },
+        files,
+        options.format_size(wasted)
+    )
+}
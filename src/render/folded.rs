@@ -0,0 +1,50 @@
+//! Folded stacks for flamegraph tools such as `inferno`, `flamegraph.pl` and speedscope.
+//!
+//! Each line is the path from the root down to one file, with components
+//! joined by `;`, followed by a space and the file size in bytes (allocated
+//! bytes with `--disk-usage`):
+//!
+//! ```text
+//! crate;src;main.rs 1834
+//! ```
+//!
+//! Directories cut off by `--depth` appear as a single line carrying their
+//! whole size. Since `;` and line breaks are part of the format, they are
+//! replaced by `_` inside names. Empty files are left out.
+
+use std::io::{self, Write};
+
+use crate::render::RenderOptions;
+use crate::tree::{FileInfo, SizeTree};
+
+/// Write one folded stack line per file shown under `options`.
+pub fn write_folded(out: &mut dyn Write, tree: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    let mut stack = vec![frame(&tree.root)];
+    write_stacks(out, &tree.root, options, 0, &mut stack)
+}
+
+fn write_stacks(
+    out: &mut dyn Write,
+    dir: &FileInfo,
+    options: &RenderOptions,
+    depth: usize,
+    stack: &mut Vec<String>,
+) -> io::Result<()> {
+    for child in options.visible_children(dir) {
+        stack.push(frame(child));
+
+        if child.is_dir && options.shows_depth(depth + 1) {
+            write_stacks(out, child, options, depth + 1, stack)?;
+        } else if options.size_of(child) > 0 {
+            writeln!(out, "{} {}", stack.join(";"), options.size_of(child))?;
+        }
+
+        stack.pop();
+    }
+
+    Ok(())
+}
+
+fn frame(node: &FileInfo) -> String {
+    node.name().replace([';', '\n', '\r'], "_")
+}
@@ -0,0 +1,40 @@
+//! Self-contained HTML report with a collapsible, sortable tree.
+//!
+//! The scanned tree is embedded as the JSON document from
+//! [`json`](crate::render::json) and drawn by inline script, so the file needs
+//! no network access to open.
+
+use std::io::{self, Write};
+
+use crate::render::json::write_json;
+use crate::render::{escape_markup, RenderOptions, SizeMeasure};
+use crate::size::SizeUnits;
+use crate::tree::SizeTree;
+
+const TEMPLATE: &str = include_str!("report.html");
+
+/// Write a single offline HTML page for the tree shown under `options`.
+pub fn write_html(out: &mut dyn Write, tree: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    let mut data = Vec::new();
+    write_json(&mut data, tree, options)?;
+
+    // "</" would end the script element early; "<\/" means the same in JSON
+    let data = String::from_utf8_lossy(&data).trim_end().replace("</", "<\\/");
+    let title = escape_markup(&tree.root.path.to_string_lossy());
+    let si = (options.units == SizeUnits::Si).to_string();
+    // Shares of the parent follow the measure the sizes were filtered by
+    let measure = match options.measure {
+        SizeMeasure::Disk => "disk_size",
+        SizeMeasure::Apparent | SizeMeasure::Both => "size",
+    };
+
+    // Substitute around the data so nothing inside it is treated as a placeholder
+    let (head, tail) = TEMPLATE.split_once("{{DATA}}").unwrap();
+    out.write_all(head.replace("{{TITLE}}", &title).as_bytes())?;
+    out.write_all(data.as_bytes())?;
+    let tail = tail
+        .replace("{{TITLE}}", &title)
+        .replace("{{SI}}", &si)
+        .replace("{{MEASURE}}", measure);
+    out.write_all(tail.as_bytes())
+}
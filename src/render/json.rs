@@ -0,0 +1,145 @@
+//! Nested JSON output.
+//!
+//! The document is a single object:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "shared_bytes": 0,
+//!   "errors": [],
+//!   "root": {
+//!     "path": "./src",
+//!     "name": "src",
+//!     "size": 20480,
+//!     "disk_size": 28672,
+//!     "is_dir": true,
+//!     "kind": "dir",
+//!     "child_count": 2,
+//!     "files": 14,
+//!     "dirs": 3,
+//!     "children": [ ... ]
+//!   }
+//! }
+//! ```
+//!
+//! Every node carries `path`, `name`, `size` (apparent bytes counted for the
+//! node), `disk_size` (bytes allocated on disk, including a directory's own blocks),
+//! `is_dir`, `kind` (`"file"`, `"dir"` or `"symlink"`) and `child_count`, the
+//! number of direct children found by the scan before any filtering, plus
+//! `files` and `dirs`, the number of each anywhere beneath the node. Symlinks
+//! also carry `symlink_target`; `is_dir` and the sizes describe the target when
+//! the link was followed and the link itself otherwise. Directories whose
+//! contents were deliberately not read carry `skipped` with the reason,
+//! `"cycle"`, `"mount point"` or `"already counted"`, and directories with something unreadable in
+//! or below them carry `"partial": true`, meaning their totals are lower bounds.
+//! Files with more than one hard link carry `hard_link`, an object with the
+//! `dev` and `ino` identifying the file and its `nlink` link count; their sizes
+//! are the share attributed by `--hard-links`, so a later link reads 0 under
+//! `first`, and nodes with the same `dev` and `ino` are one file on disk.
+//! Directories within `--depth` also carry `children`, holding the
+//! children that pass `--min-size` in display order; it is absent on files and
+//! on directories below the depth limit. Paths that are not valid UTF-8 are
+//! converted lossily.
+//!
+//! `shared_bytes` counts the apparent bytes of hard-linked files that were
+//! counted once rather than once per link. `errors` lists every path that
+//! could not be read as `{"path": ..., "message": ...}`, in path order.
+//!
+//! `version` is [`SCHEMA_VERSION`]; it is bumped whenever a field is removed or
+//! changes meaning, while new fields may be added without a bump.
+
+use std::io::{self, Write};
+
+use crate::render::RenderOptions;
+use crate::tree::{FileInfo, SizeTree};
+
+/// Version of the JSON document described in the module docs.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Write the whole tree as one JSON document.
+pub fn write_json(out: &mut dyn Write, tree: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"version\":{},\"shared_bytes\":{},\"errors\":[",
+        SCHEMA_VERSION, tree.shared_bytes
+    )?;
+    for (i, error) in tree.errors.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(
+            out,
+            "{{\"path\":{},\"message\":{}}}",
+            json_string(&error.path.to_string_lossy()),
+            json_string(&error.message)
+        )?;
+    }
+    write!(out, "],\"root\":")?;
+    write_node(out, &tree.root, options, 0)?;
+    writeln!(out, "}}")
+}
+
+fn write_node(out: &mut dyn Write, node: &FileInfo, options: &RenderOptions, depth: usize) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"path\":{},\"name\":{},\"size\":{},\"disk_size\":{},\"is_dir\":{},\"kind\":\"{}\",\"child_count\":{},\"files\":{},\"dirs\":{}",
+        json_string(&node.path.to_string_lossy()),
+        json_string(&node.name()),
+        node.size,
+        node.disk_size,
+        node.is_dir,
+        node.kind(),
+        node.children.len(),
+        node.files,
+        node.dirs
+    )?;
+
+    if let Some(target) = &node.symlink_target {
+        write!(out, ",\"symlink_target\":{}", json_string(&target.to_string_lossy()))?;
+    }
+    if let Some(reason) = node.skipped {
+        write!(out, ",\"skipped\":\"{}\"", reason)?;
+    }
+    if node.partial {
+        write!(out, ",\"partial\":true")?;
+    }
+    if let Some(link) = node.hard_link {
+        write!(
+            out,
+            ",\"hard_link\":{{\"dev\":{},\"ino\":{},\"nlink\":{}}}",
+            link.dev, link.ino, link.nlink
+        )?;
+    }
+
+    if node.is_dir && options.shows_depth(depth) {
+        write!(out, ",\"children\":[")?;
+        for (i, child) in options.visible_children(node).into_iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write_node(out, child, options, depth + 1)?;
+        }
+        write!(out, "]")?;
+    }
+
+    write!(out, "}}")
+}
+
+/// Quote and escape `s` as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,169 @@
+//! Renderers that turn a scanned [`SizeTree`](crate::SizeTree) into output.
+
+pub mod breakdown;
+pub mod delimited;
+pub mod diff;
+pub mod dupes;
+pub mod folded;
+pub mod html;
+pub mod json;
+pub mod ndjson;
+pub mod svg;
+pub mod text;
+pub mod top;
+
+use std::cmp::Reverse;
+use std::time::SystemTime;
+
+use crate::age::format_age;
+use crate::size::{format_size_in, SizeUnits};
+use crate::tree::FileInfo;
+
+/// How siblings are ordered when a tree is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Largest first
+    Size,
+    /// Alphabetical by file name
+    Name,
+    /// Most files and directories beneath first
+    Count,
+}
+
+/// Which size figure renderers show, filter and sort by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMeasure {
+    /// Apparent size, the sum of file lengths
+    Apparent,
+    /// Allocated size on disk, from st_blocks
+    Disk,
+    /// Both figures, filtering and sorting by apparent size
+    Both,
+}
+
+/// Filters and ordering shared by every renderer.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Deepest level of children to show below the root (0 shows only the root's children)
+    pub max_depth: Option<usize>,
+    /// Entries smaller than this many bytes are hidden
+    pub min_size: u64,
+    pub sort: SortOrder,
+    pub measure: SizeMeasure,
+    /// Show file and directory counts next to sizes
+    pub show_counts: bool,
+    /// Name the user owning the most bytes beneath each entry (text output only)
+    pub show_owner: bool,
+    /// Show how long ago each entry was last touched (text output only)
+    pub show_age: bool,
+    /// Colour those ages by how recent they are, with ANSI escapes (text output only)
+    pub color: bool,
+    /// Powers of 1024 or 1000 for human-readable sizes
+    pub units: SizeUnits,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            max_depth: None,
+            min_size: 0,
+            sort: SortOrder::Size,
+            measure: SizeMeasure::Apparent,
+            show_counts: false,
+            show_owner: false,
+            show_age: false,
+            color: false,
+            units: SizeUnits::Iec,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Whether children found at `depth` (0 for the root's children) should be shown.
+    pub fn shows_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// The size used for filtering and sorting under the selected measure.
+    pub fn size_of(&self, node: &FileInfo) -> u64 {
+        self.measured(node.size, node.disk_size)
+    }
+
+    /// Whichever of an apparent `size` and a `disk_size` the selected measure
+    /// filters and sorts by.
+    pub fn measured(&self, size: u64, disk_size: u64) -> u64 {
+        match self.measure {
+            SizeMeasure::Disk => disk_size,
+            SizeMeasure::Apparent | SizeMeasure::Both => size,
+        }
+    }
+
+    /// A byte count in the selected units.
+    pub fn format_size(&self, size: u64) -> String {
+        format_size_in(size, self.units)
+    }
+
+    /// Human-readable size, naming the measure whenever it is not the apparent size.
+    pub fn size_label(&self, node: &FileInfo) -> String {
+        match self.measure {
+            SizeMeasure::Apparent => self.format_size(node.size),
+            SizeMeasure::Disk => format!("{} on disk", self.format_size(node.disk_size)),
+            SizeMeasure::Both => format!(
+                "{} apparent, {} on disk",
+                self.format_size(node.size),
+                self.format_size(node.disk_size)
+            ),
+        }
+    }
+
+    /// File and directory counts for a directory, or nothing when counts are off.
+    pub fn count_label(&self, node: &FileInfo) -> Option<String> {
+        if !self.show_counts || !node.is_dir {
+            return None;
+        }
+        Some(format!(
+            "{} {}, {} {}",
+            node.files,
+            if node.files == 1 { "file" } else { "files" },
+            node.dirs,
+            if node.dirs == 1 { "dir" } else { "dirs" }
+        ))
+    }
+
+    /// How long before `now` the entry's timestamp falls, or nothing when ages are off.
+    pub fn age_label(&self, node: &FileInfo, now: SystemTime) -> Option<String> {
+        if !self.show_age {
+            return None;
+        }
+        Some(match node.time {
+            Some(time) => format!("{} old", format_age(now.duration_since(time).unwrap_or_default())),
+            None => "age unknown".to_string(),
+        })
+    }
+
+    /// The children of `dir` that pass the size filter, in display order.
+    pub fn visible_children<'a>(&self, dir: &'a FileInfo) -> Vec<&'a FileInfo> {
+        let mut files: Vec<&FileInfo> = dir
+            .children
+            .iter()
+            .filter(|child| self.size_of(child) >= self.min_size)
+            .collect();
+
+        // Children are already in name order, which the stable sorts keep for ties
+        match self.sort {
+            SortOrder::Size => files.sort_by_key(|file| Reverse(self.size_of(file))),
+            SortOrder::Count => files.sort_by_key(|file| Reverse(file.entry_count())),
+            SortOrder::Name => {}
+        }
+
+        files
+    }
+}
+
+/// Escape text for use inside HTML or SVG markup, including attribute values.
+pub(crate) fn escape_markup(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
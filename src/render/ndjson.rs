@@ -0,0 +1,69 @@
+//! Newline-delimited JSON, one object per entry, written while the scan runs.
+//!
+//! Each line looks like:
+//!
+//! ```json
+//! {"path":"./src/main.rs","parent":"./src","depth":2,"size":1834,"disk_size":4096,"files":0,"dirs":0,"kind":"file"}
+//! ```
+//!
+//! `kind` is `"file"`, `"dir"` or `"symlink"`, `size` is the apparent size and
+//! `disk_size` the allocated size, both in exact bytes, and `depth` is 0 for
+//! the root. `files` and `dirs` count the entries anywhere beneath a directory.
+//! `parent` is `null` on the root line. Symlinks add
+//! `symlink_target`, directories that were not read add `skipped` with the
+//! reason, and directories with something unreadable in or below them add
+//! `"partial":true`. Entries arrive in the order their sizes become known, so a
+//! directory always follows its descendants and the root comes last.
+
+use std::io::{self, Write};
+
+use crate::render::json::json_string;
+use crate::render::RenderOptions;
+use crate::scan::StreamEntry;
+
+/// Write `entry` as one line if it passes the `--depth` and `--min-size` filters.
+pub fn write_ndjson_entry(out: &mut dyn Write, entry: &StreamEntry, options: &RenderOptions) -> io::Result<()> {
+    let size = options.measured(entry.size, entry.disk_size);
+
+    // Depth limits count the root's children as level 0, like the text tree
+    if entry.depth > 0 && (!options.shows_depth(entry.depth - 1) || size < options.min_size) {
+        return Ok(());
+    }
+
+    let parent = match entry.path.parent() {
+        Some(parent) if entry.depth > 0 => json_string(&parent.to_string_lossy()),
+        _ => "null".to_string(),
+    };
+
+    let kind = if entry.symlink_target.is_some() {
+        "symlink"
+    } else if entry.is_dir {
+        "dir"
+    } else {
+        "file"
+    };
+
+    write!(
+        out,
+        "{{\"path\":{},\"parent\":{},\"depth\":{},\"size\":{},\"disk_size\":{},\"files\":{},\"dirs\":{},\"kind\":\"{}\"",
+        json_string(&entry.path.to_string_lossy()),
+        parent,
+        entry.depth,
+        entry.size,
+        entry.disk_size,
+        entry.files,
+        entry.dirs,
+        kind
+    )?;
+
+    if let Some(target) = entry.symlink_target {
+        write!(out, ",\"symlink_target\":{}", json_string(&target.to_string_lossy()))?;
+    }
+    if let Some(reason) = entry.skipped {
+        write!(out, ",\"skipped\":\"{}\"", reason)?;
+    }
+    if entry.partial {
+        write!(out, ",\"partial\":true")?;
+    }
+    writeln!(out, "}}")
+}
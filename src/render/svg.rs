@@ -0,0 +1,213 @@
+//! Squarified treemap rendered as a standalone SVG image.
+//!
+//! Every entry is a rectangle whose area is proportional to its size.
+//! Directories show their name in a header strip above their children, and
+//! every rectangle has a hover title with the full path and size.
+
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use crate::render::{escape_markup, RenderOptions};
+use crate::tree::{FileInfo, SizeTree};
+
+const HEADER_HEIGHT: f64 = 16.0;
+const PADDING: f64 = 2.0;
+const CHAR_WIDTH: f64 = 7.0;
+
+/// Dimensions of the generated image, in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            width: 1280,
+            height: 800,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Write the tree shown under `options` as a treemap.
+pub fn write_svg(
+    out: &mut dyn Write,
+    tree: &SizeTree,
+    options: &RenderOptions,
+    svg: &SvgOptions,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"11\">",
+        w = svg.width,
+        h = svg.height
+    )?;
+
+    let bounds = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: svg.width as f64,
+        h: svg.height as f64,
+    };
+    write_node(out, &tree.root, bounds, options, 0, 0.0)?;
+
+    writeln!(out, "</svg>")
+}
+
+fn write_node(
+    out: &mut dyn Write,
+    node: &FileInfo,
+    rect: Rect,
+    options: &RenderOptions,
+    depth: usize,
+    hue: f64,
+) -> io::Result<()> {
+    let lightness = (85.0 - depth as f64 * 8.0).max(40.0);
+    let saturation = if node.is_dir { 45 } else { 25 };
+
+    writeln!(
+        out,
+        "<g><title>{} ({})</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"hsl({:.0},{}%,{:.0}%)\" stroke=\"#fff\" stroke-width=\"0.5\"/>",
+        escape_markup(&node.path.to_string_lossy()),
+        options.size_label(node),
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        hue,
+        saturation,
+        lightness
+    )?;
+    write_label(out, node, rect, options)?;
+    writeln!(out, "</g>")?;
+
+    if !node.is_dir || !options.shows_depth(depth) {
+        return Ok(());
+    }
+
+    // Leave room for the directory's own label above its children
+    let inner = Rect {
+        x: rect.x + PADDING,
+        y: rect.y + HEADER_HEIGHT,
+        w: rect.w - 2.0 * PADDING,
+        h: rect.h - HEADER_HEIGHT - PADDING,
+    };
+    if inner.w < 1.0 || inner.h < 1.0 {
+        return Ok(());
+    }
+
+    let mut children = options.visible_children(node);
+    children.retain(|child| options.size_of(child) > 0);
+    children.sort_by_key(|child| Reverse(options.size_of(child)));
+
+    let total: u64 = children.iter().map(|child| options.size_of(child)).sum();
+    if total == 0 {
+        return Ok(());
+    }
+
+    let scale = inner.w * inner.h / total as f64;
+    let areas: Vec<f64> = children
+        .iter()
+        .map(|child| options.size_of(child) as f64 * scale)
+        .collect();
+
+    for (i, (child, child_rect)) in children.iter().zip(squarify(&areas, inner)).enumerate() {
+        // Top-level entries each get their own hue, which their descendants keep
+        let child_hue = if depth == 0 { (i as f64 * 137.5) % 360.0 } else { hue };
+        write_node(out, child, child_rect, options, depth + 1, child_hue)?;
+    }
+
+    Ok(())
+}
+
+fn write_label(out: &mut dyn Write, node: &FileInfo, rect: Rect, options: &RenderOptions) -> io::Result<()> {
+    if rect.w < 3.0 * CHAR_WIDTH || rect.h < HEADER_HEIGHT - 2.0 {
+        return Ok(());
+    }
+
+    let label = format!("{} ({})", node.name(), options.format_size(options.size_of(node)));
+    let max_chars = ((rect.w - 6.0) / CHAR_WIDTH) as usize;
+    let label: String = if label.chars().count() > max_chars {
+        let mut short: String = label.chars().take(max_chars.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    } else {
+        label
+    };
+
+    writeln!(
+        out,
+        "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+        rect.x + 3.0,
+        rect.y + 12.0,
+        escape_markup(&label)
+    )
+}
+
+// Lay out `areas` (largest first, summing to the area of `rect`) using the
+// squarified algorithm: fill rows along the shorter side of the remaining
+// space, adding items to a row while that keeps its worst aspect ratio down.
+fn squarify(areas: &[f64], mut rect: Rect) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut start = 0;
+
+    while start < areas.len() {
+        let side = rect.w.min(rect.h);
+        let mut end = start + 1;
+        let mut best = worst_ratio(&areas[start..end], side);
+        while end < areas.len() {
+            let ratio = worst_ratio(&areas[start..=end], side);
+            if ratio > best {
+                break;
+            }
+            best = ratio;
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let thickness = row.iter().sum::<f64>() / side;
+        let mut offset = 0.0;
+
+        if rect.w >= rect.h {
+            // Column on the left edge
+            for area in row {
+                let h = area / thickness;
+                rects.push(Rect { x: rect.x, y: rect.y + offset, w: thickness, h });
+                offset += h;
+            }
+            rect.x += thickness;
+            rect.w -= thickness;
+        } else {
+            // Row along the top edge
+            for area in row {
+                let w = area / thickness;
+                rects.push(Rect { x: rect.x + offset, y: rect.y, w, h: thickness });
+                offset += w;
+            }
+            rect.y += thickness;
+            rect.h -= thickness;
+        }
+
+        start = end;
+    }
+
+    rects
+}
+
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side2 = side * side;
+    let sum2 = sum * sum;
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::hardlinks::HardLinkPolicy;
+use crate::owners::{top_owners, IdNames};
+use crate::render::RenderOptions;
+use crate::tree::{FileInfo, SizeTree};
+
+// Who owns the most bytes under each node, and what to call them
+struct Owners<'a> {
+    top: HashMap<&'a Path, u32>,
+    names: IdNames,
+}
+
+/// Write the indented emoji tree, starting with a line for the root.
+pub fn write_text(out: &mut dyn Write, tree: &SizeTree, options: &RenderOptions) -> io::Result<()> {
+    let root = &tree.root;
+    let owners = options.show_owner.then(|| Owners {
+        top: top_owners(root, options),
+        names: IdNames::users(),
+    });
+    writeln!(
+        out,
+        "{}{} ({}){}",
+        root.path.display(),
+        target_suffix(root),
+        details(root, options),
+        note_suffix(root, owners.as_ref())
+    )?;
+
+    // Skip displaying the tree if the root directory is smaller than min_size
+    if options.size_of(root) < options.min_size {
+        writeln!(out, "No entries meet the minimum size criteria.")?;
+        return Ok(());
+    }
+
+    write_children(out, root, "", options, owners.as_ref(), 0)?;
+
+    // Always apparent bytes, since links share their blocks on disk as well
+    if tree.shared_bytes > 0 {
+        let attributed = match tree.hard_links {
+            HardLinkPolicy::First => "counted once at the first path",
+            HardLinkPolicy::Split => "split evenly between the paths",
+        };
+        writeln!(
+            out,
+            "Hard links: {} of apparent size reached through several paths, {}",
+            options.format_size(tree.shared_bytes),
+            attributed
+        )?;
+    }
+
+    Ok(())
+}
+
+// What goes in the parentheses: the size, plus counts and age when asked for
+fn details(file: &FileInfo, options: &RenderOptions) -> String {
+    let mut details = options.size_label(file);
+    if let Some(counts) = options.count_label(file) {
+        details.push_str(&format!(", {}", counts));
+    }
+    let now = SystemTime::now();
+    if let Some(age) = options.age_label(file, now) {
+        match age_color(file, now).filter(|_| options.color) {
+            Some(color) => details.push_str(&format!(", \x1b[{}m{}\x1b[0m", color, age)),
+            None => details.push_str(&format!(", {}", age)),
+        }
+    }
+    details
+}
+
+// ANSI colour for an entry's age: green within a month, yellow within a year,
+// red beyond that, and none when the age is unknown
+fn age_color(file: &FileInfo, now: SystemTime) -> Option<&'static str> {
+    const MONTH: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+    const YEAR: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+    let age = now.duration_since(file.time?).unwrap_or_default();
+    Some(if age < MONTH {
+        "32"
+    } else if age < YEAR {
+        "33"
+    } else {
+        "31"
+    })
+}
+
+fn target_suffix(file: &FileInfo) -> String {
+    match &file.symlink_target {
+        Some(target) => format!(" -> {}", target.display()),
+        None => String::new(),
+    }
+}
+
+// Explains why a directory looks emptier than it is, and who fills it
+fn note_suffix(file: &FileInfo, owners: Option<&Owners>) -> String {
+    let mut note = match file.skipped {
+        Some(reason) => format!(" [{}]", reason),
+        None => String::new(),
+    };
+    if file.partial {
+        note.push_str(" [partial]");
+    }
+    if let Some(owners) = owners {
+        if let Some(uid) = owners.top.get(file.path.as_path()) {
+            note.push_str(&format!(" [owner: {}]", owners.names.name(*uid)));
+        }
+    }
+    note
+}
+
+fn write_children(
+    out: &mut dyn Write,
+    dir: &FileInfo,
+    prefix: &str,
+    options: &RenderOptions,
+    owners: Option<&Owners>,
+    current_depth: usize,
+) -> io::Result<()> {
+    if !options.shows_depth(current_depth) {
+        return Ok(());
+    }
+
+    let files = options.visible_children(dir);
+
+    let total_entries = files.len();
+    for (i, file) in files.iter().enumerate() {
+        let is_last_entry = i == total_entries - 1;
+        
+        // Choose an icon based on file type
+        let icon = if file.symlink_target.is_some() {
+            "🔗"
+        } else if file.is_dir {
+            "📂"
+        } else {
+            "📄"
+        };
+
+        let connector = if is_last_entry {
+            "└── "
+        } else {
+            "├── "
+        };
+
+        // Print the entry with an icon
+        writeln!(
+            out,
+            "{}{}{} {}{} ({}){}",
+            prefix,
+            connector,
+            icon,
+            file.name(),
+            target_suffix(file),
+            details(file, options),
+            note_suffix(file, owners)
+        )?;
+
+        // Recurse into directories
+        if file.is_dir {
+            let new_prefix = if is_last_entry {
+                format!("{}    ", prefix)
+            } else {
+                format!("{}│   ", prefix)
+            };
+
+            write_children(out, file, &new_prefix, options, owners, current_depth + 1)?;
+        }
+    }
+
+    Ok(())
+}
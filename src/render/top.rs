@@ -0,0 +1,34 @@
+//! The `--top` listing: one line per entry, largest first, with paths relative
+//! to the root.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::render::{RenderOptions, SizeMeasure};
+
+/// Write a heading naming `what` was ranked, then one line per entry in `largest`.
+pub fn write_top(
+    out: &mut dyn Write,
+    root: &Path,
+    largest: &[(u64, PathBuf)],
+    what: &str,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let measure = match options.measure {
+        SizeMeasure::Disk => " on disk",
+        SizeMeasure::Apparent | SizeMeasure::Both => "",
+    };
+    writeln!(out, "Largest {}{} under {}:", what, measure, root.display())?;
+
+    if largest.is_empty() {
+        writeln!(out, "No entries meet the minimum size criteria.")?;
+        return Ok(());
+    }
+
+    for (size, path) in largest {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        writeln!(out, "{:>10}  {}", options.format_size(*size), relative.display())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,960 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::age::TimeField;
+use crate::error::{ScanError, SizeError};
+use crate::glob::{self, Pattern};
+use crate::hardlinks::{self, dedup_hard_links, HardLinkPolicy};
+use crate::ignore::{IgnoreFiles, IgnoreRules};
+use crate::tree::{FileInfo, HardLink, SizeTree, SkipReason};
+
+/// Which symbolic links are resolved while scanning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Symlinks are entries of their own and are never resolved
+    #[default]
+    Never,
+    /// Only a symlink given as the scan root is resolved
+    Roots,
+    /// Every symlink is resolved, but a link back to a directory above it, or to
+    /// a directory already counted elsewhere, is not entered
+    Always,
+}
+
+/// Options controlling how a tree is scanned.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Number of worker threads; 1 walks the tree serially on the calling thread.
+    pub threads: usize,
+    /// How files with several hard links inside the scan are counted
+    pub hard_links: HardLinkPolicy,
+    pub symlinks: SymlinkPolicy,
+    /// Leave directories on a different device than the root unread
+    pub one_file_system: bool,
+    /// Entries matching these patterns are left out, and excluded directories are never read
+    pub exclude: Vec<Pattern>,
+    /// When not empty, only files matching one of these patterns, or inside a
+    /// directory that does, are counted
+    pub include: Vec<Pattern>,
+    /// Whether `.gitignore`, `.ignore` and git's excludes filter the scan
+    pub ignore_files: IgnoreFiles,
+    /// Which timestamp is recorded and compared against the cutoffs below
+    pub time_field: TimeField,
+    /// When set, only files last touched at or before this instant are counted
+    pub older_than: Option<SystemTime>,
+    /// When set, only files last touched at or after this instant are counted
+    pub newer_than: Option<SystemTime>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            threads: 1,
+            hard_links: HardLinkPolicy::First,
+            symlinks: SymlinkPolicy::Never,
+            one_file_system: false,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            ignore_files: IgnoreFiles::Disregard,
+            time_field: TimeField::Modified,
+            older_than: None,
+            newer_than: None,
+        }
+    }
+}
+
+/// Walk `root` once and return the full size tree.
+///
+/// Children are kept in name order, so the result does not depend on the
+/// number of threads used. Hard links are resolved after the walk, in tree
+/// order, so each inode is counted once. A directory reached again through a
+/// symlink or a bind mount is only read once; the other paths to it are marked
+/// [`SkipReason::AlreadyCounted`]. Walking serially, the first path in tree
+/// order is the one read; with several threads it is whichever got there
+/// first, though the totals are the same either way.
+pub fn scan(root: &Path, options: ScanOptions) -> Result<SizeTree, SizeError> {
+    let (mut root, mut errors) = if options.threads > 1 {
+        scan_parallel(root, &options)?
+    } else {
+        scan_serial(root, &options)?
+    };
+
+    let shared_bytes = dedup_hard_links(&mut root, options.hard_links);
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(SizeTree {
+        root,
+        errors,
+        shared_bytes,
+        hard_links: options.hard_links,
+    })
+}
+
+/// An entry reported by [`scan_streaming`] once its size is known.
+#[derive(Debug)]
+pub struct StreamEntry<'a> {
+    pub path: &'a Path,
+    /// 0 for the root, 1 for its children, and so on
+    pub depth: usize,
+    pub size: u64,
+    /// Allocated bytes on disk, including the directory's own blocks
+    pub disk_size: u64,
+    pub is_dir: bool,
+    /// Owning user and group ids (0 on platforms without them)
+    pub uid: u32,
+    pub gid: u32,
+    /// Where the entry points, if it is a symlink
+    pub symlink_target: Option<&'a Path>,
+    pub skipped: Option<SkipReason>,
+    /// Files anywhere beneath the entry
+    pub files: u64,
+    /// Directories anywhere beneath the entry
+    pub dirs: u64,
+    /// Something in or below the directory could not be read
+    pub partial: bool,
+}
+
+// What a directory adds up to in the streaming walk
+#[derive(Default)]
+struct Totals {
+    size: u64,
+    disk_size: u64,
+    files: u64,
+    dirs: u64,
+    partial: bool,
+}
+
+/// Walk `root` serially without keeping the tree, calling `on_entry` for each
+/// file as it is read and for each directory as soon as its last descendant
+/// has been counted. The root is reported last. Returns every path that could
+/// not be read, in the order they were met.
+///
+/// Entries are read in name order, so hard links are counted at the first path
+/// in tree order, as with [`HardLinkPolicy::First`]. `Split` is not applied:
+/// each size is reported before the later links to it are found.
+///
+/// An error returned by `on_entry` stops the walk and is passed back.
+pub fn scan_streaming(
+    root: &Path,
+    options: ScanOptions,
+    on_entry: &mut dyn FnMut(&StreamEntry) -> io::Result<()>,
+) -> Result<Vec<ScanError>, SizeError> {
+    let mut errors = Vec::new();
+
+    match inspect_root(root, &options)? {
+        Found::Dir(mut dir) => {
+            let visited = Visited::default();
+            let mut seen_links = HashSet::new();
+            let totals = stream_dir(&mut dir, 0, &options, &visited, &mut seen_links, &mut errors, on_entry)?;
+            let (uid, gid) = owner(&dir.metadata);
+            on_entry(&StreamEntry {
+                path: root,
+                depth: 0,
+                size: totals.size,
+                disk_size: totals.disk_size,
+                is_dir: true,
+                uid,
+                gid,
+                symlink_target: dir.symlink_target.as_deref(),
+                skipped: dir.skipped,
+                files: totals.files,
+                dirs: totals.dirs,
+                partial: totals.partial,
+            })?;
+        }
+        Found::Leaf(node) => {
+            on_entry(&StreamEntry {
+                path: root,
+                depth: 0,
+                size: node.size,
+                disk_size: node.disk_size,
+                is_dir: false,
+                uid: node.uid,
+                gid: node.gid,
+                symlink_target: node.symlink_target.as_deref(),
+                skipped: None,
+                files: 0,
+                dirs: 0,
+                partial: false,
+            })?;
+        }
+    }
+
+    Ok(errors)
+}
+
+fn stream_dir(
+    dir: &mut PendingDir,
+    depth: usize,
+    options: &ScanOptions,
+    visited: &Visited,
+    seen_links: &mut HashSet<(u64, u64)>,
+    errors: &mut Vec<ScanError>,
+    on_entry: &mut dyn FnMut(&StreamEntry) -> io::Result<()>,
+) -> io::Result<Totals> {
+    claim(dir, visited);
+    let listing = read_children(dir, options);
+    let mut totals = Totals {
+        disk_size: allocated_size(&dir.metadata),
+        partial: !listing.errors.is_empty(),
+        ..Totals::default()
+    };
+    errors.extend(listing.errors);
+
+    // Files and subdirectories are visited together in name order, the order the
+    // full tree is walked in, so both count a hard link at the same path
+    let mut files = listing.children.into_iter().peekable();
+    let mut subdirs = listing.subdirs.into_iter().peekable();
+
+    loop {
+        let file_first = match (files.peek(), subdirs.peek()) {
+            (Some(file), Some(subdir)) => file.path.file_name() < subdir.path.file_name(),
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+
+        if file_first {
+            let mut file = files.next().unwrap();
+            if let Some(link) = file.hard_link {
+                if !seen_links.insert(hardlinks::key(link)) {
+                    file.size = 0;
+                    file.disk_size = 0;
+                }
+            }
+
+            on_entry(&StreamEntry {
+                path: &file.path,
+                depth: depth + 1,
+                size: file.size,
+                disk_size: file.disk_size,
+                is_dir: false,
+                uid: file.uid,
+                gid: file.gid,
+                symlink_target: file.symlink_target.as_deref(),
+                skipped: None,
+                files: 0,
+                dirs: 0,
+                partial: false,
+            })?;
+            totals.size += file.size;
+            totals.disk_size += file.disk_size;
+            totals.files += 1;
+            continue;
+        }
+
+        let mut subdir = subdirs.next().unwrap();
+        let sub = stream_dir(&mut subdir, depth + 1, options, visited, seen_links, errors, on_entry)?;
+        let (uid, gid) = owner(&subdir.metadata);
+        on_entry(&StreamEntry {
+            path: &subdir.path,
+            depth: depth + 1,
+            size: sub.size,
+            disk_size: sub.disk_size,
+            is_dir: true,
+            uid,
+            gid,
+            symlink_target: subdir.symlink_target.as_deref(),
+            skipped: subdir.skipped,
+            files: sub.files,
+            dirs: sub.dirs,
+            partial: sub.partial,
+        })?;
+        totals.size += sub.size;
+        totals.disk_size += sub.disk_size;
+        totals.files += sub.files;
+        totals.dirs += sub.dirs + 1;
+        totals.partial |= sub.partial;
+    }
+
+    Ok(totals)
+}
+
+// Walk the tree once, keeping every node so the renderer never touches the disk
+fn scan_serial(path: &Path, options: &ScanOptions) -> Result<(FileInfo, Vec<ScanError>), SizeError> {
+    let mut errors = Vec::new();
+    let root = match inspect_root(path, options)? {
+        Found::Dir(dir) => scan_dir(dir, options, &Visited::default(), &mut errors),
+        Found::Leaf(node) => node,
+    };
+    Ok((root, errors))
+}
+
+fn scan_dir(
+    mut dir: PendingDir,
+    options: &ScanOptions,
+    visited: &Visited,
+    errors: &mut Vec<ScanError>,
+) -> FileInfo {
+    claim(&mut dir, visited);
+    let listing = read_children(&dir, options);
+    let unreadable = !listing.errors.is_empty();
+    errors.extend(listing.errors);
+
+    let mut children = listing.children;
+    children.extend(
+        listing
+            .subdirs
+            .into_iter()
+            .map(|subdir| scan_dir(subdir, options, visited, errors)),
+    );
+    finish_dir(dir, children, unreadable, options)
+}
+
+// Same traversal as `scan_serial`, but directories are spread over `threads` workers.
+// Each worker pops its own newest job and steals the oldest job of another
+// worker when it runs dry; the tree is stitched back together at the end.
+fn scan_parallel(path: &Path, options: &ScanOptions) -> Result<(FileInfo, Vec<ScanError>), SizeError> {
+    let root = match inspect_root(path, options)? {
+        Found::Dir(dir) => dir,
+        Found::Leaf(node) => return Ok((node, Vec::new())),
+    };
+    let threads = options.threads;
+
+    let work = Work {
+        queues: (0..threads).map(|_| Mutex::default()).collect(),
+        pending: AtomicUsize::new(0),
+        next_id: AtomicUsize::new(0),
+        idle: Mutex::new(()),
+        wake: Condvar::new(),
+    };
+    let visited = Visited::default();
+    work.push(0, None, root);
+
+    let results: Vec<DirJobResult> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (work, visited) = (&work, &visited);
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    while let Some(mut job) = work.next_job(worker) {
+                        let _finished = JobGuard(work);
+                        claim(&mut job.dir, visited);
+                        let listing = read_children(&job.dir, options);
+                        for subdir in listing.subdirs {
+                            work.push(worker, Some(job.id), subdir);
+                        }
+                        done.push(DirJobResult {
+                            job,
+                            children: listing.children,
+                            unreadable: !listing.errors.is_empty(),
+                            errors: listing.errors,
+                        });
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    let mut errors = Vec::new();
+    let mut slots: Vec<Option<DirJobResult>> = (0..results.len()).map(|_| None).collect();
+    for mut result in results {
+        let id = result.job.id;
+        errors.append(&mut result.errors);
+        slots[id] = Some(result);
+    }
+
+    // A directory always gets a larger id than its parent, so walking the ids
+    // backwards finishes every directory before it is attached to its parent
+    for id in (1..slots.len()).rev() {
+        let result = slots[id].take().unwrap();
+        let parent = result.job.parent.unwrap();
+        let node = finish_dir(result.job.dir, result.children, result.unreadable, options);
+        slots[parent].as_mut().unwrap().children.push(node);
+    }
+
+    let root = slots[0].take().unwrap();
+    let root = finish_dir(root.job.dir, root.children, root.unreadable, options);
+    Ok((root, errors))
+}
+
+struct DirJob {
+    id: usize,
+    parent: Option<usize>,
+    dir: PendingDir,
+}
+
+struct DirJobResult {
+    job: DirJob,
+    children: Vec<FileInfo>,
+    unreadable: bool,
+    errors: Vec<ScanError>,
+}
+
+// The jobs shared by the workers of `scan_parallel`
+struct Work {
+    queues: Vec<Mutex<VecDeque<DirJob>>>,
+    /// Jobs queued or being read; the walk is over when it drops to 0
+    pending: AtomicUsize,
+    next_id: AtomicUsize,
+    /// Workers with nothing to do wait on `wake` for a new job or the end of the walk
+    idle: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Work {
+    fn push(&self, worker: usize, parent: Option<usize>, dir: PendingDir) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.queues[worker].lock().unwrap().push_back(DirJob {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            parent,
+            dir,
+        });
+        let _idle = self.idle.lock().unwrap();
+        self.wake.notify_one();
+    }
+
+    fn finish(&self) {
+        if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let _idle = self.idle.lock().unwrap();
+            self.wake.notify_all();
+        }
+    }
+
+    fn take(&self, worker: usize) -> Option<DirJob> {
+        // Prefer our own newest job, which keeps each worker depth-first
+        if let Some(job) = self.queues[worker].lock().unwrap().pop_back() {
+            return Some(job);
+        }
+
+        // Otherwise steal the oldest job from another worker
+        (1..self.queues.len()).find_map(|offset| {
+            let victim = (worker + offset) % self.queues.len();
+            self.queues[victim].lock().unwrap().pop_front()
+        })
+    }
+
+    fn next_job(&self, worker: usize) -> Option<DirJob> {
+        loop {
+            if let Some(job) = self.take(worker) {
+                return Some(job);
+            }
+
+            // Looked at again under the lock that `push` and `finish` signal
+            // with, so neither can slip in between the check and the wait
+            let idle = self.idle.lock().unwrap();
+            if let Some(job) = self.take(worker) {
+                return Some(job);
+            }
+            // Nothing queued and nothing in flight means the walk is over
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            drop(self.wake.wait(idle).unwrap());
+        }
+    }
+}
+
+// Marks a job finished when dropped, even by a panic while reading it, so the
+// other workers still see the walk end and the panic reaches `thread::scope`
+struct JobGuard<'a>(&'a Work);
+
+impl Drop for JobGuard<'_> {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+// Device and inode of a directory, used to recognise it when reached again
+type DirId = (u64, u64);
+
+// Every directory read so far in one scan, shared by all workers
+type Visited = Mutex<HashSet<DirId>>;
+
+// Record that `dir` is about to be read, or mark it as already counted when
+// another path to it got there first
+fn claim(dir: &mut PendingDir, visited: &Visited) {
+    if dir.skipped.is_some() {
+        return;
+    }
+    if let Some(&id) = dir.ancestors.last() {
+        if !visited.lock().unwrap().insert(id) {
+            dir.skipped = Some(SkipReason::AlreadyCounted);
+        }
+    }
+}
+
+// A directory that has been found but not read yet
+struct PendingDir {
+    path: PathBuf,
+    metadata: fs::Metadata,
+    symlink_target: Option<PathBuf>,
+    /// Set when the directory must not be read
+    skipped: Option<SkipReason>,
+    /// This directory and every directory above it
+    ancestors: Vec<DirId>,
+    /// Path below the scan root, `/`-separated, empty for the root itself
+    relative: String,
+    /// Whether the directory or one above it matched an include pattern
+    included: bool,
+    /// Whether the directory or one above it is ignored by an ignore file
+    ignored: bool,
+    /// Ignore rules for the directory's entries, when they need checking
+    ignore: Option<Arc<IgnoreRules>>,
+}
+
+impl PendingDir {
+    fn device(&self) -> Option<u64> {
+        self.ancestors.last().map(|id| id.0)
+    }
+
+    fn root_device(&self) -> Option<u64> {
+        self.ancestors.first().map(|id| id.0)
+    }
+}
+
+enum Found {
+    Leaf(FileInfo),
+    Dir(PendingDir),
+}
+
+fn inspect_root(path: &Path, options: &ScanOptions) -> io::Result<Found> {
+    let follow = options.symlinks != SymlinkPolicy::Never;
+    let mut found = inspect(path.to_path_buf(), follow, &[], options.time_field)?;
+    if let Found::Dir(dir) = &mut found {
+        dir.included = options.include.is_empty();
+        if options.ignore_files != IgnoreFiles::Disregard {
+            dir.ignore = Some(IgnoreRules::for_root(path));
+        }
+    }
+    Ok(found)
+}
+
+// Work out what `path` is, resolving it first when it is a symlink and
+// `follow` is set. A directory that is also one of its `ancestors` is a cycle
+// and is kept without being read.
+fn inspect(path: PathBuf, follow: bool, ancestors: &[DirId], time_field: TimeField) -> io::Result<Found> {
+    let metadata = fs::symlink_metadata(&path)?;
+
+    let (metadata, symlink_target) = if metadata.file_type().is_symlink() {
+        let target = fs::read_link(&path).unwrap_or_default();
+        match fs::metadata(&path) {
+            Ok(resolved) if follow => (resolved, Some(target)),
+            // Unfollowed and dangling links count as the link itself
+            _ => (metadata, Some(target)),
+        }
+    } else {
+        (metadata, None)
+    };
+
+    if !metadata.is_dir() {
+        let mut node = leaf(path, &metadata, time_field);
+        node.symlink_target = symlink_target;
+        return Ok(Found::Leaf(node));
+    }
+
+    let mut skipped = None;
+    let mut ancestors = ancestors.to_vec();
+    if let Some(id) = dir_id(&metadata) {
+        if ancestors.contains(&id) {
+            skipped = Some(SkipReason::Cycle);
+        }
+        ancestors.push(id);
+    }
+
+    Ok(Found::Dir(PendingDir {
+        path,
+        metadata,
+        symlink_target,
+        skipped,
+        ancestors,
+        relative: String::new(),
+        included: false,
+        ignored: false,
+        ignore: None,
+    }))
+}
+
+// The contents of one directory: its non-directory children as finished
+// nodes, the subdirectories that still need to be walked, and whatever could
+// not be read
+#[derive(Default)]
+struct Listing {
+    children: Vec<FileInfo>,
+    subdirs: Vec<PendingDir>,
+    errors: Vec<ScanError>,
+}
+
+fn read_children(dir: &PendingDir, options: &ScanOptions) -> Listing {
+    let mut listing = Listing::default();
+
+    if dir.skipped.is_some() {
+        return listing;
+    }
+
+    let follow = options.symlinks == SymlinkPolicy::Always;
+
+    let entries = match fs::read_dir(&dir.path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            listing.errors.push(ScanError::new(dir.path.clone(), &err));
+            return listing;
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                listing.errors.push(ScanError::new(dir.path.clone(), &err));
+                continue;
+            }
+        };
+
+        let relative = if dir.relative.is_empty() {
+            entry.file_name().to_string_lossy().into_owned()
+        } else {
+            format!("{}/{}", dir.relative, entry.file_name().to_string_lossy())
+        };
+
+        let found = match inspect(entry.path(), follow, &dir.ancestors, options.time_field) {
+            Ok(found) => found,
+            Err(err) => {
+                listing.errors.push(ScanError::new(entry.path(), &err));
+                continue;
+            }
+        };
+
+        // Excluded entries are dropped here, before a directory is ever read
+        let is_dir = matches!(found, Found::Dir(_));
+        if glob::matches_any(&options.exclude, &relative, is_dir) {
+            continue;
+        }
+        let included = dir.included || glob::matches_any(&options.include, &relative, is_dir);
+
+        // Git's own metadata is neither tracked nor ignored, so it is left out
+        if options.ignore_files != IgnoreFiles::Disregard && is_dir && entry.file_name() == ".git" {
+            continue;
+        }
+        let ignored = dir.ignored
+            || dir
+                .ignore
+                .as_ref()
+                .is_some_and(|rules| rules.is_ignored(&relative, is_dir));
+        if ignored && options.ignore_files == IgnoreFiles::Respect {
+            continue;
+        }
+
+        match found {
+            Found::Leaf(node) => {
+                let wanted = options.ignore_files != IgnoreFiles::OnlyIgnored || ignored;
+                if included && wanted && in_age_window(&node, options) {
+                    listing.children.push(node);
+                }
+            }
+            Found::Dir(mut subdir) => {
+                // Everything below an ignored directory is ignored, so its rules don't matter
+                if let Some(rules) = dir.ignore.as_ref().filter(|_| !ignored) {
+                    subdir.ignore = Some(IgnoreRules::for_dir(rules, &subdir.path, &relative));
+                }
+                subdir.ignored = ignored;
+                subdir.relative = relative;
+                subdir.included = included;
+                if options.one_file_system
+                    && subdir.skipped.is_none()
+                    && subdir.device() != dir.root_device()
+                {
+                    subdir.skipped = Some(SkipReason::MountPoint);
+                }
+                listing.subdirs.push(subdir);
+            }
+        }
+    }
+
+    // Walkers descend in name order, so the first path to a directory or a
+    // hard link is the same on every run
+    listing.children.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+    listing.subdirs.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+    listing
+}
+
+fn leaf(path: PathBuf, metadata: &fs::Metadata, time_field: TimeField) -> FileInfo {
+    let (uid, gid) = owner(metadata);
+    FileInfo {
+        path,
+        size: metadata.len(),
+        disk_size: allocated_size(metadata),
+        is_dir: false,
+        hard_link: hard_link(metadata),
+        uid,
+        gid,
+        time: time_field.of(metadata),
+        symlink_target: None,
+        skipped: None,
+        files: 0,
+        dirs: 0,
+        partial: false,
+        children: Vec::new(),
+    }
+}
+
+// Whether a file passes `--older-than` and `--newer-than`; files without the
+// timestamp only pass when neither is set
+fn in_age_window(node: &FileInfo, options: &ScanOptions) -> bool {
+    match node.time {
+        Some(time) => {
+            options.older_than.is_none_or(|cutoff| time <= cutoff)
+                && options.newer_than.is_none_or(|cutoff| time >= cutoff)
+        }
+        None => options.older_than.is_none() && options.newer_than.is_none(),
+    }
+}
+
+// Children are kept in name order so every traversal yields the same tree.
+// `unreadable` says whether the directory's own listing hit an error.
+fn finish_dir(
+    dir: PendingDir,
+    mut children: Vec<FileInfo>,
+    unreadable: bool,
+    options: &ScanOptions,
+) -> FileInfo {
+    children.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+
+    // Like du, a directory's allocated size includes its own blocks
+    let own_disk_size = allocated_size(&dir.metadata);
+
+    let files = children
+        .iter()
+        .map(|child| child.files + u64::from(!child.is_dir))
+        .sum();
+    let dirs = children
+        .iter()
+        .map(|child| child.dirs + u64::from(child.is_dir))
+        .sum();
+    let (uid, gid) = owner(&dir.metadata);
+    // A directory is as recent as what it holds; its own timestamp moves whenever
+    // an entry is added or removed, and reading it bumps its atime. An empty
+    // directory falls back to its own, unless age filters emptied it.
+    let filtered = options.older_than.is_some() || options.newer_than.is_some();
+    let time = children
+        .iter()
+        .filter_map(|child| child.time)
+        .max()
+        .or_else(|| if filtered { None } else { options.time_field.of(&dir.metadata) });
+
+    FileInfo {
+        path: dir.path,
+        size: children.iter().map(|child| child.size).sum(),
+        disk_size: own_disk_size + children.iter().map(|child| child.disk_size).sum::<u64>(),
+        is_dir: true,
+        hard_link: None,
+        uid,
+        gid,
+        time,
+        symlink_target: dir.symlink_target,
+        skipped: dir.skipped,
+        files,
+        dirs,
+        partial: unreadable || children.iter().any(|child| child.partial),
+        children,
+    }
+}
+
+// Bytes actually allocated, from st_blocks (always in 512-byte units)
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(unix)]
+fn hard_link(metadata: &fs::Metadata) -> Option<HardLink> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| HardLink {
+        dev: metadata.dev(),
+        ino: metadata.ino(),
+        nlink: metadata.nlink(),
+    })
+}
+
+#[cfg(unix)]
+fn owner(metadata: &fs::Metadata) -> (u32, u32) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.uid(), metadata.gid())
+}
+
+#[cfg(unix)]
+fn dir_id(metadata: &fs::Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &fs::Metadata) -> (u32, u32) {
+    (0, 0)
+}
+
+#[cfg(not(unix))]
+fn dir_id(_metadata: &fs::Metadata) -> Option<DirId> {
+    None
+}
+
+#[cfg(not(unix))]
+fn hard_link(_metadata: &fs::Metadata) -> Option<HardLink> {
+    None
+}
+
+// Without st_blocks the best estimate is the apparent size
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Lay out a few hundred files over nested directories with varied sizes
+    fn build_fixture(root: &Path) {
+        for a in 0..6 {
+            for b in 0..5 {
+                let dir = root.join(format!("dir{}", a)).join(format!("sub{}", b));
+                fs::create_dir_all(&dir).unwrap();
+                for c in 0..8 {
+                    let len = (a * 997 + b * 131 + c * 17) % 4096;
+                    fs::write(dir.join(format!("file{}", c)), vec![0u8; len]).unwrap();
+                }
+            }
+            fs::write(root.join(format!("top{}", a)), vec![0u8; a * 100]).unwrap();
+        }
+        fs::create_dir_all(root.join("empty")).unwrap();
+    }
+
+    #[test]
+    fn parallel_scan_matches_serial_scan() {
+        let root = std::env::temp_dir().join(format!("sizetree-parallel-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        build_fixture(&root);
+
+        let serial = scan_serial(&root, &ScanOptions::default()).unwrap();
+        assert!(serial.1.is_empty());
+        for threads in [2, 4, 8] {
+            let options = ScanOptions {
+                threads,
+                ..ScanOptions::default()
+            };
+            assert_eq!(scan_parallel(&root, &options).unwrap(), serial);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Find the node at `relative` below `root`
+    fn find<'a>(root: &'a FileInfo, relative: &str) -> &'a FileInfo {
+        let path = root.path.join(relative);
+        let mut node = root;
+        while node.path != path {
+            node = node.children.iter().find(|child| path.starts_with(&child.path)).unwrap();
+        }
+        node
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_end_in_a_cycle() {
+        let root = std::env::temp_dir().join(format!("sizetree-cycle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("a/b/file"), vec![0u8; 3000]).unwrap();
+        std::os::unix::fs::symlink("..", root.join("a/b/up")).unwrap();
+        // Not a cycle, but `a` must still only be counted once
+        std::os::unix::fs::symlink("../a", root.join("c/loop")).unwrap();
+
+        let options = ScanOptions {
+            symlinks: SymlinkPolicy::Always,
+            ..ScanOptions::default()
+        };
+        let tree = scan(&root, options.clone()).unwrap();
+        assert_eq!(find(&tree.root, "a/b/up").skipped, Some(SkipReason::Cycle));
+        assert_eq!(find(&tree.root, "c/loop").skipped, Some(SkipReason::AlreadyCounted));
+        assert_eq!(tree.root.size, 3000);
+        assert_eq!(tree.root.files, 1);
+
+        let parallel = scan_parallel(&root, &ScanOptions { threads: 4, ..options.clone() }).unwrap().0;
+        assert_eq!((parallel.size, parallel.files, parallel.dirs), (3000, 1, tree.root.dirs));
+
+        let mut streamed = None;
+        scan_streaming(&root, options, &mut |entry| {
+            if entry.depth == 0 {
+                streamed = Some((entry.size, entry.files, entry.dirs));
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(streamed, Some((3000, 1, tree.root.dirs)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unfollowed_symlinks_are_listed_as_links() {
+        let root = std::env::temp_dir().join(format!("sizetree-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/file"), vec![0u8; 3000]).unwrap();
+        std::os::unix::fs::symlink("a", root.join("dir_link")).unwrap();
+        std::os::unix::fs::symlink("a/file", root.join("file_link")).unwrap();
+
+        let tree = scan(&root, ScanOptions::default()).unwrap();
+        for name in ["dir_link", "file_link"] {
+            let link = find(&tree.root, name);
+            assert_eq!(link.kind(), "symlink");
+            assert!(!link.is_dir);
+            assert!(link.children.is_empty());
+            assert_eq!(link.size, fs::symlink_metadata(&link.path).unwrap().len());
+        }
+        assert_eq!(find(&tree.root, "dir_link").symlink_target, Some(PathBuf::from("a")));
+        assert_eq!(tree.root.files, 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streaming_counts_hard_links_where_the_tree_does() {
+        let root = std::env::temp_dir().join(format!("sizetree-stream-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("a/b/big.bin"), vec![0u8; 5000]).unwrap();
+        fs::hard_link(root.join("a/b/big.bin"), root.join("c/hard.bin")).unwrap();
+        // Sorts after the directory `a` but would be read first if files came first
+        fs::hard_link(root.join("a/b/big.bin"), root.join("a/z.bin")).unwrap();
+
+        let tree = scan(&root, ScanOptions::default()).unwrap();
+        let mut expected = Vec::new();
+        let mut stack = vec![&tree.root];
+        while let Some(node) = stack.pop() {
+            expected.push((node.path.clone(), node.size));
+            stack.extend(&node.children);
+        }
+        expected.sort();
+
+        let mut streamed = Vec::new();
+        scan_streaming(&root, ScanOptions::default(), &mut |entry| {
+            streamed.push((entry.path.to_path_buf(), entry.size));
+            Ok(())
+        })
+        .unwrap();
+        streamed.sort();
+
+        assert_eq!(streamed, expected);
+        assert!(streamed.contains(&(root.join("a/b/big.bin"), 5000)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::error::SizeError;
+
+/// Which powers sizes are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, GiB, TiB, PiB, EiB
+    #[default]
+    Iec,
+    /// Powers of 1000: kB, MB, GB, TB, PB, EB
+    Si,
+}
+
+impl SizeUnits {
+    fn base(self) -> u64 {
+        match self {
+            SizeUnits::Iec => 1024,
+            SizeUnits::Si => 1000,
+        }
+    }
+
+    fn labels(self) -> [&'static str; 6] {
+        match self {
+            SizeUnits::Iec => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            SizeUnits::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+}
+
+/// Format a byte count using the largest binary unit that fits.
+pub fn format_size(size: u64) -> String {
+    format_size_in(size, SizeUnits::Iec)
+}
+
+/// Format a byte count using the largest unit of `units` that fits.
+pub fn format_size_in(size: u64, units: SizeUnits) -> String {
+    let base = units.base();
+    if size < base {
+        return format!("{} B", size);
+    }
+
+    // Find the largest power of the base that is not above the size
+    let mut unit = 1;
+    let mut index = 0;
+    while index + 1 < units.labels().len() && size / base >= unit * base {
+        unit *= base;
+        index += 1;
+    }
+    let unit = unit * base;
+
+    format!("{:.2} {}", size as f64 / unit as f64, units.labels()[index])
+}
+
+/// Parse a size such as `1.5MiB`, `500K`, `2TB` or `42` into bytes.
+///
+/// Single letters and IEC suffixes (`K`, `Ki`, `KiB`, up to `E`, `Ei`, `EiB`)
+/// are powers of 1024; SI suffixes (`kB`, `MB`, up to `EB`) are powers of 1000.
+/// Case is ignored. Sizes that don't fit in 64 bits are rejected.
+pub fn parse_size(size_str: &str) -> Result<u64, SizeError> {
+    let size_str = size_str.trim().to_uppercase();
+
+    if size_str.is_empty() {
+        return Err(SizeError::ParseError("Empty size string".to_string()));
+    }
+
+    let split = size_str
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(size_str.len());
+    let (num_str, unit) = size_str.split_at(split);
+    let num_str = num_str.trim();
+
+    let multiplier: u64 = match unit {
+        "" | "B" => 1,
+        _ => {
+            let (prefix, rest) = unit.split_at(1);
+            let power = match "KMGTPE".find(prefix) {
+                Some(index) => index as u32 + 1,
+                None => return Err(SizeError::ParseError(format!("Unknown unit: {}", unit))),
+            };
+            match rest {
+                "" | "I" | "IB" => 1024u64.pow(power),
+                "B" => 1000u64.pow(power),
+                _ => return Err(SizeError::ParseError(format!("Unknown unit: {}", unit))),
+            }
+        }
+    };
+
+    let too_large = || SizeError::ParseError(format!("Size too large: {}", size_str));
+
+    // Whole numbers are multiplied exactly; fractions go through f64
+    if let Ok(num) = num_str.parse::<u64>() {
+        return num.checked_mul(multiplier).ok_or_else(too_large);
+    }
+
+    let num = num_str.parse::<f64>()
+        .map_err(|_| SizeError::ParseError(format!("Invalid number: {}", num_str)))?;
+    if !num.is_finite() || num < 0.0 {
+        return Err(SizeError::ParseError(format!("Invalid number: {}", num_str)));
+    }
+
+    // 2^64 itself is exactly representable, and is the first value that doesn't fit
+    let bytes = num * multiplier as f64;
+    if bytes >= u64::MAX as f64 {
+        return Err(too_large());
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iec_and_si_suffixes() {
+        assert_eq!(parse_size("42").unwrap(), 42);
+        assert_eq!(parse_size("42B").unwrap(), 42);
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("1Ki").unwrap(), 1024);
+        assert_eq!(parse_size("1KiB").unwrap(), 1024);
+        assert_eq!(parse_size("1KB").unwrap(), 1000);
+        assert_eq!(parse_size("1kB").unwrap(), 1000);
+        assert_eq!(parse_size("1.5MiB").unwrap(), 1536 * 1024);
+        assert_eq!(parse_size("2GB").unwrap(), 2_000_000_000);
+        assert_eq!(parse_size("1EiB").unwrap(), 1 << 60);
+        assert_eq!(parse_size("1EB").unwrap(), 1_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn sizes_up_to_u64_max() {
+        assert_eq!(parse_size("18446744073709551615").unwrap(), u64::MAX);
+        assert_eq!(parse_size("15.9E").unwrap(), (15.9 * (1u64 << 60) as f64) as u64);
+        assert!(parse_size("18446744073709551616").is_err());
+        assert!(parse_size("16EiB").is_err());
+        assert!(parse_size("16E").is_err());
+        assert!(parse_size("18.5EB").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        for bad in ["", "K", "1XB", "1KiBB", "-1K", "1..5M", "inf", "NaN"] {
+            assert!(parse_size(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn picks_the_largest_unit_that_fits() {
+        assert_eq!(format_size_in(1023, SizeUnits::Iec), "1023 B");
+        assert_eq!(format_size_in(1024, SizeUnits::Iec), "1.00 KiB");
+        assert_eq!(format_size_in(999, SizeUnits::Si), "999 B");
+        assert_eq!(format_size_in(1000, SizeUnits::Si), "1.00 kB");
+        assert_eq!(format_size_in(1536 * 1024, SizeUnits::Iec), "1.50 MiB");
+        assert_eq!(format_size_in(1 << 60, SizeUnits::Iec), "1.00 EiB");
+        assert_eq!(format_size_in(u64::MAX, SizeUnits::Iec), "16.00 EiB");
+        assert_eq!(format_size_in(u64::MAX, SizeUnits::Si), "18.45 EB");
+        assert_eq!(format_size(2048), "2.00 KiB");
+    }
+}
@@ -0,0 +1,269 @@
+//! Saving a scanned tree to a file and loading it back, so two scans taken at
+//! different times can be compared.
+//!
+//! A snapshot is UTF-8 text. It starts with a `sizetree-snapshot <version>`
+//! line and a `shared_bytes <n>` line, followed by one line per node in
+//! depth-first order, root first:
+//!
+//! ```text
+//! <depth>\t<kind>\t<size>\t<disk_size>\t<files>\t<dirs>\t<partial>\t<name>[\t<symlink target>]
+//! ```
+//!
+//! `depth` is 0 for the root, whose name field holds the scanned path. `kind`
+//! is `dir` or `file`, describing what was measured, so a followed symlink to
+//! a directory is a `dir` and a link that was not followed is a `file`.
+//! `partial` is `0` or `1`, and the target is only present on symlinks. Tabs,
+//! line breaks and backslashes in names are written as `\t`, `\n`, `\r` and
+//! `\\`; paths that are not valid UTF-8 are converted lossily. Hard-link
+//! identities, owners, timestamps, skip reasons and the individual scan errors
+//! are not kept.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::error::SizeError;
+use crate::hardlinks::HardLinkPolicy;
+use crate::tree::{FileInfo, SizeTree};
+
+/// Version written on the first line of every snapshot.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const MAGIC: &str = "sizetree-snapshot";
+
+/// Write every node of `tree` in the snapshot format.
+pub fn write_snapshot(out: &mut dyn Write, tree: &SizeTree) -> io::Result<()> {
+    writeln!(out, "{} {}", MAGIC, SNAPSHOT_VERSION)?;
+    writeln!(out, "shared_bytes {}", tree.shared_bytes)?;
+    write_node(out, &tree.root, &tree.root.path.to_string_lossy(), 0)
+}
+
+fn write_node(out: &mut dyn Write, node: &FileInfo, name: &str, depth: usize) -> io::Result<()> {
+    write!(
+        out,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        depth,
+        if node.is_dir { "dir" } else { "file" },
+        node.size,
+        node.disk_size,
+        node.files,
+        node.dirs,
+        u8::from(node.partial),
+        escape(name)
+    )?;
+    if let Some(target) = &node.symlink_target {
+        write!(out, "\t{}", escape(&target.to_string_lossy()))?;
+    }
+    writeln!(out)?;
+
+    for child in &node.children {
+        write_node(out, child, &child.name(), depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Load a tree written by [`write_snapshot`].
+pub fn read_snapshot(input: &mut dyn BufRead) -> Result<SizeTree, SizeError> {
+    let mut lines = input.lines();
+
+    let header = lines.next().transpose()?.unwrap_or_default();
+    match header.split_once(' ') {
+        Some((MAGIC, version)) if version == SNAPSHOT_VERSION.to_string() => {}
+        Some((MAGIC, version)) => {
+            return Err(SizeError::SnapshotError(format!("Unsupported snapshot version: {}", version)))
+        }
+        _ => return Err(SizeError::SnapshotError("Not a sizetree snapshot".to_string())),
+    }
+
+    let shared_bytes = match lines.next().transpose()? {
+        Some(line) => match line.split_once(' ') {
+            Some(("shared_bytes", n)) => parse_number(n, 2)?,
+            _ => return Err(malformed(2)),
+        },
+        None => return Err(malformed(2)),
+    };
+
+    // Directories whose children are still being read, innermost last
+    let mut open: Vec<FileInfo> = Vec::new();
+    let mut root = None;
+
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        let number = index + 3;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 8 || fields.len() > 9 {
+            return Err(malformed(number));
+        }
+
+        let depth = parse_number(fields[0], number)? as usize;
+        if root.is_some() || depth > open.len() || (depth == 0) != open.is_empty() {
+            return Err(malformed(number));
+        }
+        while open.len() > depth {
+            close(&mut open);
+        }
+
+        let name = unescape(fields[7]);
+        let path = match open.last() {
+            Some(parent) => parent.path.join(name),
+            None => PathBuf::from(name),
+        };
+        if fields[1] != "dir" && fields[1] != "file" {
+            return Err(malformed(number));
+        }
+        let symlink_target = fields.get(8).map(|target| PathBuf::from(unescape(target)));
+
+        let node = FileInfo {
+            path,
+            size: parse_number(fields[2], number)?,
+            disk_size: parse_number(fields[3], number)?,
+            is_dir: fields[1] == "dir",
+            hard_link: None,
+            uid: 0,
+            gid: 0,
+            time: None,
+            symlink_target,
+            skipped: None,
+            files: parse_number(fields[4], number)?,
+            dirs: parse_number(fields[5], number)?,
+            partial: fields[6] == "1",
+            children: Vec::new(),
+        };
+
+        // Only directories can hold the lines that follow
+        if node.is_dir {
+            open.push(node);
+        } else if let Some(parent) = open.last_mut() {
+            parent.children.push(node);
+        } else {
+            root = Some(node);
+        }
+    }
+
+    while open.len() > 1 {
+        close(&mut open);
+    }
+    let root = match root.or_else(|| open.pop()) {
+        Some(root) => root,
+        None => return Err(SizeError::SnapshotError("Snapshot has no entries".to_string())),
+    };
+
+    Ok(SizeTree {
+        root,
+        errors: Vec::new(),
+        shared_bytes,
+        // Not recorded; only the text footer reads it, and diffs don't print one
+        hard_links: HardLinkPolicy::default(),
+    })
+}
+
+// Attach the innermost open directory to its parent
+fn close(open: &mut Vec<FileInfo>) {
+    let node = open.pop().unwrap();
+    open.last_mut().unwrap().children.push(node);
+}
+
+fn parse_number(field: &str, line: usize) -> Result<u64, SizeError> {
+    field.parse().map_err(|_| malformed(line))
+}
+
+fn malformed(line: usize) -> SizeError {
+    SizeError::SnapshotError(format!("Malformed snapshot line {}", line))
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: PathBuf, size: u64, children: Vec<FileInfo>) -> FileInfo {
+        FileInfo {
+            is_dir: !children.is_empty() || path.ends_with("empty"),
+            files: children.iter().map(|child| child.files + u64::from(!child.is_dir)).sum(),
+            dirs: children.iter().map(|child| child.dirs + u64::from(child.is_dir)).sum(),
+            path,
+            size,
+            disk_size: size * 2,
+            hard_link: None,
+            uid: 0,
+            gid: 0,
+            time: None,
+            symlink_target: None,
+            skipped: None,
+            partial: false,
+            children,
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let root = PathBuf::from("/scan\\root");
+        let deep = root.join("a\tb").join("new\nline");
+        let mut link = node(root.join("link"), 4, Vec::new());
+        link.symlink_target = Some(PathBuf::from("../tab\there\\"));
+        let mut a = node(
+            root.join("a\tb"),
+            30,
+            vec![
+                node(deep.clone(), 20, vec![node(deep.join("back\\slash"), 20, Vec::new())]),
+                node(root.join("a\tb").join("empty"), 0, Vec::new()),
+                node(root.join("a\tb").join("z"), 10, Vec::new()),
+            ],
+        );
+        a.partial = true;
+        // A file after a nested directory has to be attached back at depth 1
+        let tree = SizeTree {
+            root: node(root.clone(), 35, vec![a, link, node(root.join("last\r"), 1, Vec::new())]),
+            errors: Vec::new(),
+            shared_bytes: 7,
+            hard_links: HardLinkPolicy::First,
+        };
+
+        let mut written = Vec::new();
+        write_snapshot(&mut written, &tree).unwrap();
+        assert_eq!(String::from_utf8_lossy(&written).lines().count(), 2 + 8);
+
+        let read = read_snapshot(&mut written.as_slice()).unwrap();
+        assert_eq!(read.root, tree.root);
+        assert_eq!(read.shared_bytes, 7);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let err = read_snapshot(&mut "sizetree-snapshot 99\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Snapshot error: Unsupported snapshot version: 99");
+        let err = read_snapshot(&mut "sizetree-snapshot 1\nshared_bytes 0\n1\tfile\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Snapshot error: Malformed snapshot line 3");
+    }
+}
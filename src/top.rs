@@ -0,0 +1,97 @@
+//! The N largest entries of a scan, kept in a bounded heap so memory does not
+//! grow with the size of the tree.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use sizetree::{scan_streaming, Largest, ScanOptions};
+//!
+//! let mut largest = Largest::new(10);
+//! scan_streaming(Path::new("."), ScanOptions::default(), &mut |entry| {
+//!     if !entry.is_dir {
+//!         largest.offer(entry.size, entry.path);
+//!     }
+//!     Ok(())
+//! })?;
+//! for (size, path) in largest.into_sorted_vec() {
+//!     println!("{} {}", size, path.display());
+//! }
+//! # Ok::<(), sizetree::SizeError>(())
+//! ```
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+
+/// Keeps the `limit` largest sizes offered, with their paths.
+///
+/// Equal sizes are ranked by path, so the result does not depend on the order
+/// entries were offered in.
+#[derive(Debug, Clone)]
+pub struct Largest {
+    limit: usize,
+    // A min-heap, so the entry to evict is always on top
+    heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ranked {
+    size: u64,
+    path: PathBuf,
+}
+
+// Larger sizes rank higher, then earlier paths
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.size
+            .cmp(&other.size)
+            .then_with(|| other.path.cmp(&self.path))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Largest {
+    pub fn new(limit: usize) -> Self {
+        Largest {
+            limit,
+            heap: BinaryHeap::with_capacity(limit + 1),
+        }
+    }
+
+    /// Consider one entry. The path is only copied if the entry makes the cut.
+    pub fn offer(&mut self, size: u64, path: &Path) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.heap.len() == self.limit {
+            let Reverse(smallest) = self.heap.peek().unwrap();
+            let beats_smallest = match size.cmp(&smallest.size) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => path < smallest.path.as_path(),
+            };
+            if !beats_smallest {
+                return;
+            }
+            self.heap.pop();
+        }
+        self.heap.push(Reverse(Ranked {
+            size,
+            path: path.to_path_buf(),
+        }));
+    }
+
+    /// The kept entries, largest first.
+    pub fn into_sorted_vec(self) -> Vec<(u64, PathBuf)> {
+        // Ascending order of `Reverse` is descending order of size
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| (ranked.size, ranked.path))
+            .collect()
+    }
+}
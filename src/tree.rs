@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::error::ScanError;
+use crate::hardlinks::HardLinkPolicy;
+
+/// The result of a scan: the root node with every descendant beneath it.
+#[derive(Debug, PartialEq)]
+pub struct SizeTree {
+    pub root: FileInfo,
+    /// Everything that could not be read, in path order
+    pub errors: Vec<ScanError>,
+    /// Apparent bytes that hard links would have counted again
+    pub shared_bytes: u64,
+    /// How the size of each hard-linked file was attributed to its links
+    pub hard_links: HardLinkPolicy,
+}
+
+/// One file or directory in the tree.
+///
+/// A directory's `size` is the sum of its children, which are kept in name order.
+#[derive(Debug, PartialEq)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    /// Apparent size in bytes, as reported by `len()`
+    pub size: u64,
+    /// Bytes allocated on disk; for a directory this includes its own blocks
+    pub disk_size: u64,
+    pub is_dir: bool,
+    /// Set on files with more than one hard link
+    pub hard_link: Option<HardLink>,
+    /// Owning user and group ids (0 on platforms without them)
+    pub uid: u32,
+    pub gid: u32,
+    /// The timestamp selected by [`ScanOptions::time_field`](crate::ScanOptions::time_field);
+    /// for a directory, the newest of everything counted beneath it, or its own
+    /// when it is empty
+    pub time: Option<SystemTime>,
+    /// Where the entry points, if it is a symlink; `is_dir` and the sizes
+    /// describe the target when the link was followed and the link otherwise
+    pub symlink_target: Option<PathBuf>,
+    /// Set on directories whose contents were deliberately not read
+    pub skipped: Option<SkipReason>,
+    /// Files (anything but a directory) anywhere beneath this node
+    pub files: u64,
+    /// Directories anywhere beneath this node
+    pub dirs: u64,
+    /// Set on directories where something in or below them could not be read,
+    /// so the totals are lower bounds
+    pub partial: bool,
+    pub children: Vec<FileInfo>,
+}
+
+/// Why a directory's contents were left out of the scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The directory is also one of its own ancestors, reached through a symlink
+    Cycle,
+    /// The directory is on another filesystem and the scan stays on one
+    MountPoint,
+    /// The directory was already counted at an earlier path, reached again
+    /// through a symlink or a bind mount
+    AlreadyCounted,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Cycle => write!(f, "cycle"),
+            SkipReason::MountPoint => write!(f, "mount point"),
+            SkipReason::AlreadyCounted => write!(f, "already counted"),
+        }
+    }
+}
+
+/// Inode identity of a file with more than one hard link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HardLink {
+    pub dev: u64,
+    pub ino: u64,
+    /// Link count reported by the filesystem, including links outside the scan
+    pub nlink: u64,
+}
+
+impl FileInfo {
+    /// The final path component, or the full path for a root like `.` or `/`.
+    pub fn name(&self) -> Cow<'_, str> {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => self.path.to_string_lossy(),
+        }
+    }
+
+    /// `"symlink"`, `"dir"` or `"file"`.
+    pub fn kind(&self) -> &'static str {
+        if self.symlink_target.is_some() {
+            "symlink"
+        } else if self.is_dir {
+            "dir"
+        } else {
+            "file"
+        }
+    }
+
+    /// Number of files and directories anywhere beneath this node.
+    pub fn entry_count(&self) -> u64 {
+        self.files + self.dirs
+    }
+}